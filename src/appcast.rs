@@ -0,0 +1,238 @@
+//! Sparkle `appcast.xml` feeds, as published by many desktop applications.
+//!
+//! The deserializer matches local names, so `sparkle:version` is read as `version`.
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use reqwest::Client;
+use serde::Deserialize;
+use std::cmp::Reverse;
+use url::Url;
+
+use crate::cli::CommandArgs;
+use crate::source::{PrismArtifact, PrismRelease, ReleaseSource, ReleaseType, paginate};
+use crate::system::{compare_tags, newest_release};
+
+#[derive(Deserialize)]
+struct Rss {
+    channel: Channel,
+}
+
+#[derive(Deserialize)]
+struct Channel {
+    #[serde(rename = "item", default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Item {
+    title: Option<String>,
+    #[serde(rename = "pubDate")]
+    pub_date: Option<String>,
+    description: Option<String>,
+    version: Option<String>,
+    #[serde(rename = "shortVersionString")]
+    short_version: Option<String>,
+    #[serde(rename = "releaseNotesLink")]
+    release_notes_link: Option<String>,
+    #[serde(rename = "minimumSystemVersion")]
+    minimum_system_version: Option<String>,
+    channel: Option<String>,
+    #[serde(rename = "enclosure", default)]
+    enclosures: Vec<Enclosure>,
+}
+
+#[derive(Deserialize)]
+struct Enclosure {
+    #[serde(rename = "@url")]
+    url: Url,
+    #[serde(rename = "@length", default)]
+    length: usize,
+    #[serde(rename = "@type")]
+    mime_type: Option<String>,
+    #[serde(rename = "@version")]
+    version: Option<String>,
+    #[serde(rename = "@shortVersionString")]
+    short_version: Option<String>,
+}
+
+impl Item {
+    /// The user facing version, which is what `--prism-version` holds.
+    fn tag(&self) -> Option<String> {
+        let enclosure = self.enclosures.first();
+        self.short_version
+            .clone()
+            .or_else(|| enclosure.and_then(|e| e.short_version.clone()))
+            .or_else(|| self.version.clone())
+            .or_else(|| enclosure.and_then(|e| e.version.clone()))
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.pub_date
+            .as_deref()
+            .and_then(|date| DateTime::parse_from_rfc2822(date.trim()).ok())
+            .map(|date| date.with_timezone(&Utc))
+            .unwrap_or_default()
+    }
+
+    fn channel(&self) -> Option<ReleaseType> {
+        // items without a channel are the regular releases
+        match self.channel.as_deref() {
+            None => Some(ReleaseType::Stable),
+            Some(channel) => ReleaseType::from_str(channel.trim(), true).ok(),
+        }
+    }
+
+    fn supports_system(&self, system_version: Option<&str>) -> bool {
+        match (self.minimum_system_version.as_deref(), system_version) {
+            (Some(minimum), Some(system)) => {
+                !compare_tags(minimum.trim().to_owned(), system.to_owned()).unwrap_or(false)
+            }
+            _ => true,
+        }
+    }
+
+    fn into_release(self) -> eyre::Result<PrismRelease> {
+        let tag = self
+            .tag()
+            .ok_or_else(|| eyre::eyre!("Appcast item without a version"))?;
+        let created_at = self.created_at();
+        let prerelease = self.channel() == Some(ReleaseType::Beta);
+        let body = match (self.description, self.release_notes_link) {
+            (Some(description), _) if !description.trim().is_empty() => Some(description),
+            (_, Some(link)) => Some(format!("Release notes: {}", link.trim())),
+            _ => None,
+        };
+        let assets = self
+            .enclosures
+            .into_iter()
+            .map(|enclosure| {
+                let name = enclosure
+                    .url
+                    .path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .unwrap_or_default()
+                    .to_string();
+                log::debug!(
+                    "Appcast enclosure {name} ({})",
+                    enclosure.mime_type.as_deref().unwrap_or("unknown type")
+                );
+                PrismArtifact {
+                    name,
+                    size_in_bytes: enclosure.length,
+                    api_url: None,
+                    mirrors: Vec::new(),
+                    sha256: None,
+                    download_url: enclosure.url,
+                }
+            })
+            .collect();
+        Ok(PrismRelease {
+            name: self.title.unwrap_or_else(|| tag.clone()),
+            tag,
+            created_at,
+            assets,
+            body,
+            prerelease,
+        })
+    }
+}
+
+/// Releases listed in a Sparkle appcast.
+pub struct AppcastSource<'a> {
+    client: Client,
+    appcast_url: Url,
+    cfg: &'a CommandArgs,
+}
+
+impl<'a> AppcastSource<'a> {
+    pub fn new(cfg: &'a CommandArgs) -> eyre::Result<Self> {
+        let appcast_url = cfg
+            .source_url
+            .clone()
+            .ok_or_else(|| eyre::eyre!("The appcast source needs the feed url in --source-url"))?;
+        Ok(Self {
+            client: Client::new(),
+            appcast_url,
+            cfg,
+        })
+    }
+
+    async fn get_items(&self) -> eyre::Result<Vec<Item>> {
+        let text = self
+            .client
+            .get(self.appcast_url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let rss: Rss = quick_xml::de::from_str(&text)?;
+        Ok(rss.channel.items)
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for AppcastSource<'_> {
+    async fn get_latest(&self) -> eyre::Result<PrismRelease> {
+        let system_version = self.cfg.system_version.as_deref();
+        let release_type = &self.cfg.release_type;
+        let items = self
+            .get_items()
+            .await?
+            .into_iter()
+            .filter(|item| {
+                item.channel()
+                    .is_some_and(|channel| release_type.includes(&channel))
+            })
+            .filter(|item| {
+                let supported = item.supports_system(system_version);
+                if !supported {
+                    log::info!(
+                        "Rejecting {:?} because it needs system version {:?}",
+                        item.tag(),
+                        item.minimum_system_version
+                    );
+                }
+                supported
+            });
+        let release = if *release_type == ReleaseType::Beta {
+            let releases = items
+                .map(Item::into_release)
+                .collect::<eyre::Result<Vec<_>>>()?;
+            newest_release(releases)
+        } else {
+            items
+                .max_by_key(|item| item.created_at())
+                .map(Item::into_release)
+                .transpose()?
+        };
+        release.ok_or_else(|| eyre::eyre!("No {release_type:?} item in {}", self.appcast_url))
+    }
+
+    async fn get_by_tag(&self, tag: &str) -> eyre::Result<PrismRelease> {
+        self.get_items()
+            .await?
+            .into_iter()
+            .find(|item| item.tag().as_deref() == Some(tag))
+            .ok_or_else(|| eyre::eyre!("No item {tag:?} in {}", self.appcast_url))?
+            .into_release()
+    }
+
+    async fn list(&self, page: u32, per_page: u32) -> eyre::Result<Vec<PrismRelease>> {
+        let release_type = &self.cfg.release_type;
+        let mut items: Vec<Item> = self
+            .get_items()
+            .await?
+            .into_iter()
+            .filter(|item| {
+                item.channel()
+                    .is_some_and(|channel| release_type.includes(&channel))
+            })
+            .collect();
+        items.sort_by_key(|item| Reverse(item.created_at()));
+        paginate(items, page, per_page)
+            .map(Item::into_release)
+            .collect()
+    }
+}
@@ -5,7 +5,7 @@ use std::path::{Path, PathBuf};
 use glob::glob;
 use indicatif::ProgressBar;
 use indicatif::ProgressStyle;
-use log::error;
+use log::{error, warn};
 
 fn ensure_folder_exists(path: &Path) -> io::Result<()> {
     if !path.exists() {
@@ -14,10 +14,52 @@ fn ensure_folder_exists(path: &Path) -> io::Result<()> {
     Ok(())
 }
 
+/// Moves a file, symlink or directory, keeping symlinks as links and the mtime of files.
 fn move_file(src: &Path, dest: &Path) -> io::Result<()> {
-    fs::copy(src, dest)?;
-    fs::remove_file(src)?;
-    Ok(())
+    // a rename keeps everything as is, it only fails across file systems
+    if fs::rename(src, dest).is_ok() {
+        return Ok(());
+    }
+    let metadata = fs::symlink_metadata(src)?;
+    if metadata.is_symlink() {
+        if fs::symlink_metadata(dest).is_ok() {
+            fs::remove_file(dest)?;
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(fs::read_link(src)?, dest)?;
+        #[cfg(not(unix))]
+        fs::copy(src, dest)?;
+    } else if metadata.is_dir() {
+        ensure_folder_exists(dest)?;
+        // read-only directories from the archive must still be emptied
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(src, fs::Permissions::from_mode(0o700))?;
+        }
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            move_file(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+        fs::remove_dir(src)?;
+        // directories can not be opened like files on Windows
+        #[cfg(unix)]
+        keep_mtime(dest, &metadata);
+        return fs::set_permissions(dest, metadata.permissions());
+    } else {
+        fs::copy(src, dest)?;
+        keep_mtime(dest, &metadata);
+    }
+    fs::remove_file(src)
+}
+
+fn keep_mtime(dest: &Path, metadata: &fs::Metadata) {
+    let restored = metadata
+        .modified()
+        .and_then(|mtime| fs::File::open(dest)?.set_modified(mtime));
+    if let Err(e) = restored {
+        warn!("Failed to keep the mtime of {}: {}", dest.display(), e);
+    }
 }
 
 pub fn load_manifest_files(root_path: &PathBuf, is_linux: bool) -> eyre::Result<Vec<PathBuf>> {
@@ -95,7 +137,11 @@ pub fn move_with_manifest(src: &PathBuf, dst: &Path, is_linux: bool) -> eyre::Re
     );
     let src = src.canonicalize()?;
     for path in file_list.iter() {
-        let path = path.canonicalize()?;
+        // only the parent is resolved, symlinks must be moved as links
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            continue;
+        };
+        let path = parent.canonicalize()?.join(name);
         let dest_path = dst.join(path.strip_prefix(&src)?);
         ensure_folder_exists(dest_path.parent().unwrap())?;
         if let Err(e) = move_file(&path, &dest_path) {
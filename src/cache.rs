@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Once;
+use std::time::{Duration, SystemTime};
+
+use crate::checksum::to_hex;
+use crate::cli::CommandArgs;
+
+/// Entries untouched for this many max ages are deleted, their `ETag` is unlikely to match.
+const EVICT_AFTER_MAX_AGES: u32 = 24;
+/// Entries are kept at least this long, so that a zero max age still revalidates.
+const MIN_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The cache directory is pruned once per run, not on every stored response.
+static PRUNE: Once = Once::new();
+
+/// API response kept on disk, revalidated with its `ETag` once older than the max age.
+#[derive(Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub etag: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+    pub body: String,
+}
+
+/// On-disk cache of API responses, keyed by url.
+pub struct Cache {
+    /// Set by `main` from the resolved root directory, nothing is cached without it.
+    dir: Option<PathBuf>,
+    max_age: Duration,
+}
+
+impl Cache {
+    pub fn new(cfg: &CommandArgs) -> Self {
+        Self {
+            dir: cfg.cache_dir.clone(),
+            max_age: cfg.cache_max_age,
+        }
+    }
+
+    fn path(&self, url: &str) -> Option<PathBuf> {
+        let name = to_hex(&Sha256::digest(url.as_bytes()));
+        Some(self.dir.as_ref()?.join(format!("{name}.json")))
+    }
+
+    pub fn load(&self, url: &str) -> Option<CachedResponse> {
+        let text = fs::read_to_string(self.path(url)?).ok()?;
+        match serde_json::from_str(&text) {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                log::warn!("Ignoring corrupt cache entry for {url}: {:?}", err);
+                None
+            }
+        }
+    }
+
+    /// Whether `entry` can be used without asking the server.
+    pub fn is_fresh(&self, entry: &CachedResponse) -> bool {
+        (Utc::now() - entry.fetched_at)
+            .to_std()
+            .is_ok_and(|age| age < self.max_age)
+    }
+
+    /// Stores a response, a cache that can not be written only costs a request.
+    pub fn store(&self, url: &str, etag: Option<String>, body: &str) {
+        let (Some(dir), Some(path)) = (&self.dir, self.path(url)) else {
+            return;
+        };
+        let entry = CachedResponse {
+            etag,
+            fetched_at: Utc::now(),
+            body: body.to_owned(),
+        };
+        let result = fs::create_dir_all(dir)
+            .map_err(eyre::Report::from)
+            .and_then(|_| Ok(serde_json::to_string(&entry)?))
+            .and_then(|text| Ok(fs::write(path, text)?));
+        if let Err(err) = result {
+            log::warn!("Failed to cache {url}: {:?}", err);
+        }
+        PRUNE.call_once(|| self.prune());
+    }
+
+    /// Deletes the entries that were neither stored nor revalidated for a long time.
+    fn prune(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let retention = (self.max_age * EVICT_AFTER_MAX_AGES).max(MIN_RETENTION);
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                log::warn!("Failed to read the cache directory {:?}: {:?}", dir, err);
+                return;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let expired = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age > retention);
+            if expired {
+                log::debug!("Evicting stale cache entry {:?}", path);
+                if let Err(err) = fs::remove_file(&path) {
+                    log::warn!("Failed to evict {:?}: {:?}", path, err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    #[test]
+    fn prune_evicts_only_stale_entries() {
+        let dir = TempDir::new().unwrap();
+        let cache = Cache {
+            dir: Some(dir.path().to_owned()),
+            max_age: Duration::from_secs(60 * 60),
+        };
+        cache.store("https://example.com/fresh", None, "{}");
+        cache.store("https://example.com/stale", None, "{}");
+        let stale = cache.path("https://example.com/stale").unwrap();
+        let two_days_ago = SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60);
+        File::options()
+            .write(true)
+            .open(&stale)
+            .unwrap()
+            .set_modified(two_days_ago)
+            .unwrap();
+        let unrelated = dir.path().join("notes.txt");
+        fs::write(&unrelated, "").unwrap();
+        File::options()
+            .write(true)
+            .open(&unrelated)
+            .unwrap()
+            .set_modified(two_days_ago)
+            .unwrap();
+
+        cache.prune();
+        assert!(cache.load("https://example.com/fresh").is_some());
+        assert!(cache.load("https://example.com/stale").is_none());
+        assert!(unrelated.exists());
+    }
+}
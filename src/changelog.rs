@@ -0,0 +1,539 @@
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+use crate::source::{PrismRelease, ReleaseSource};
+use crate::system::parse_semver;
+
+/// Releases fetched per page while looking for the skipped ones.
+const PER_PAGE: u32 = 50;
+/// Pages walked before giving up on reaching the installed version.
+const MAX_PAGES: u32 = 10;
+
+/// Release notes of every release after `installed` up to `target`, oldest first.
+pub async fn skipped_release_notes(
+    source: &dyn ReleaseSource,
+    installed: &str,
+    target: &PrismRelease,
+    include_prereleases: bool,
+) -> eyre::Result<String> {
+    let installed = parse_semver(installed)?;
+    let newest = parse_semver(&target.tag)?;
+
+    let mut skipped = Vec::new();
+    for page in 1..=MAX_PAGES {
+        let releases = source.list(page, PER_PAGE).await?;
+        let last_page = releases.len() < PER_PAGE as usize;
+        let mut reached_installed = true;
+        for release in releases {
+            let Ok(version) = parse_semver(&release.tag) else {
+                log::info!("Ignoring {:?} because it is not a version", release.tag);
+                continue;
+            };
+            if version > installed {
+                reached_installed = false;
+            }
+            if version <= installed || version > newest {
+                continue;
+            }
+            if release.prerelease && !include_prereleases && release.tag != target.tag {
+                continue;
+            }
+            if skipped.iter().all(|(v, _)| *v != version) {
+                skipped.push((version, section(&release)));
+            }
+        }
+        // listings are newest first, once a page is all older releases we are done
+        if last_page || reached_installed {
+            break;
+        }
+    }
+    if skipped.iter().all(|(v, _)| *v != newest) {
+        skipped.push((newest, section(target)));
+    }
+    skipped.sort_by(|(v1, _), (v2, _)| v1.cmp(v2));
+
+    let sections = skipped
+        .into_iter()
+        .map(|(_, section)| section)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Ok(format!("# Changelog\n\n{sections}"))
+}
+
+fn section(release: &PrismRelease) -> String {
+    let body = release.body.as_deref().unwrap_or_default().trim();
+    format!(
+        "## {} ({})\n\n{}",
+        release.tag,
+        release.created_at.format("%Y-%m-%d"),
+        if body.is_empty() {
+            "No release notes."
+        } else {
+            body
+        }
+    )
+}
+
+/// Pull request a nightly commit was merged through.
+#[derive(Clone)]
+pub struct PullRequest {
+    /// How the forge refers to it, `#12` on GitHub or `!12` on GitLab.
+    pub reference: String,
+    pub title: String,
+    pub url: Url,
+}
+
+/// Commit between the installed and the latest nightly build.
+pub struct ChangelogCommit {
+    pub sha: String,
+    pub message: String,
+    pub url: Url,
+    pub pull_request: Option<PullRequest>,
+}
+
+/// Commit of a comparison as the forge returns it, merge commits included.
+pub struct ComparedCommit {
+    pub sha: String,
+    /// First parent first, like git.
+    pub parents: Vec<String>,
+    pub message: String,
+    pub url: Url,
+}
+
+/// Pull request named by a commit message: the `(#12)` suffix of a squashed subject, or the
+/// GitHub (`Merge pull request #12`) or GitLab (`See merge request group/project!12`) merge.
+fn referenced_pull_request(
+    commit: &ComparedCommit,
+    pull_url: &impl Fn(&str) -> Option<Url>,
+) -> Option<PullRequest> {
+    let subject = commit.message.lines().next().unwrap_or_default().trim();
+    if commit.parents.len() < 2 {
+        let squashed = Regex::new(r"^(.*\S)\s+\((#|!)(\d+)\)$").unwrap();
+        let captures = squashed.captures(subject)?;
+        return Some(PullRequest {
+            reference: format!("{}{}", &captures[2], &captures[3]),
+            title: captures[1].to_owned(),
+            url: pull_url(&captures[3])?,
+        });
+    }
+    let github = Regex::new(r"^Merge pull request #(\d+)").unwrap();
+    let gitlab = Regex::new(r"(?m)^See merge request \S*!(\d+)\s*$").unwrap();
+    let (reference, number) = match github.captures(subject) {
+        Some(captures) => (format!("#{}", &captures[1]), captures[1].to_owned()),
+        None => {
+            let captures = gitlab.captures(&commit.message)?;
+            (format!("!{}", &captures[1]), captures[1].to_owned())
+        }
+    };
+    // both forges put the pull request title on the first line of the body
+    let title = commit
+        .message
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or(subject);
+    Some(PullRequest {
+        reference,
+        title: title.to_owned(),
+        url: pull_url(&number)?,
+    })
+}
+
+/// Shas of `start` and of its ancestors among `commits`.
+fn ancestors<'a>(
+    commits: &'a [ComparedCommit],
+    index: &HashMap<&str, usize>,
+    start: &'a str,
+) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    let mut pending = vec![start];
+    while let Some(sha) = pending.pop() {
+        if !seen.insert(sha) {
+            continue;
+        }
+        if let Some(&i) = index.get(sha) {
+            pending.extend(commits[i].parents.iter().map(String::as_str));
+        }
+    }
+    seen
+}
+
+/// Attributes commits to the pull request they were merged through from the commit messages,
+/// for when the forge can not tell. Merge commits are left out, they only repeat what their
+/// pull request says.
+pub fn attribute_pull_requests(
+    commits: Vec<ComparedCommit>,
+    pull_url: impl Fn(&str) -> Option<Url>,
+) -> Vec<ChangelogCommit> {
+    let index: HashMap<&str, usize> = commits
+        .iter()
+        .enumerate()
+        .map(|(i, commit)| (commit.sha.as_str(), i))
+        .collect();
+    let mut pull_requests: Vec<Option<PullRequest>> = commits
+        .iter()
+        .map(|commit| match commit.parents.len() {
+            0 | 1 => referenced_pull_request(commit, &pull_url),
+            _ => None,
+        })
+        .collect();
+    for merge in commits.iter().filter(|commit| commit.parents.len() >= 2) {
+        let Some(pull_request) = referenced_pull_request(merge, &pull_url) else {
+            continue;
+        };
+        // the merged branch is everything reachable from the second parent but not the first
+        let base = ancestors(&commits, &index, &merge.parents[0]);
+        let mut next = Some(merge.parents[1].as_str());
+        while let Some(sha) = next
+            && !base.contains(sha)
+            && let Some(&i) = index.get(sha)
+        {
+            pull_requests[i].get_or_insert_with(|| pull_request.clone());
+            next = commits[i].parents.first().map(String::as_str);
+        }
+    }
+    commits
+        .into_iter()
+        .zip(pull_requests)
+        .filter(|(commit, _)| commit.parents.len() < 2)
+        .map(|(commit, pull_request)| ChangelogCommit {
+            sha: commit.sha,
+            message: commit.message,
+            url: commit.url,
+            pull_request,
+        })
+        .collect()
+}
+
+const SECTION_TITLES: [&str; 4] = ["Features", "Fixes", "Chores", "Other changes"];
+
+/// Subject line split as a conventional commit (`type(scope)!: description`).
+struct Subject<'a> {
+    kind: Option<String>,
+    scope: Option<&'a str>,
+    breaking: bool,
+    description: &'a str,
+}
+
+impl<'a> Subject<'a> {
+    fn parse(subject: &'a str) -> Self {
+        let subject = subject.trim();
+        let pattern = Regex::new(r"^(\w+)(?:\(([^)]*)\))?(!)?:\s*(.+)$").unwrap();
+        match pattern.captures(subject) {
+            Some(captures) => Self {
+                kind: Some(captures[1].to_lowercase()),
+                scope: captures.get(2).map(|m| m.as_str()),
+                breaking: captures.get(3).is_some(),
+                description: captures.get(4).map_or(subject, |m| m.as_str()),
+            },
+            None => Self {
+                kind: None,
+                scope: None,
+                breaking: false,
+                description: subject,
+            },
+        }
+    }
+
+    fn section(&self) -> usize {
+        match self.kind.as_deref() {
+            Some("feat") => 0,
+            Some("fix") => 1,
+            Some(_) => 2,
+            None => 3,
+        }
+    }
+
+    fn describe(&self) -> String {
+        let mut text = match self.scope {
+            Some(scope) => format!("**{scope}:** {}", self.description),
+            None => self.description.to_owned(),
+        };
+        if self.breaking {
+            text = format!("**BREAKING** {text}");
+        }
+        text
+    }
+}
+
+impl ChangelogCommit {
+    fn subject(&self) -> Subject<'_> {
+        Subject::parse(self.message.lines().next().unwrap_or_default())
+    }
+
+    fn link(&self) -> String {
+        let short = &self.sha[..self.sha.len().min(7)];
+        format!("[{short}]({})", self.url)
+    }
+
+    fn co_authors(&self) -> String {
+        let names = self
+            .message
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.trim().eq_ignore_ascii_case("co-authored-by").then(|| {
+                    // drop the e-mail address
+                    value
+                        .split('<')
+                        .next()
+                        .unwrap_or_default()
+                        .trim()
+                        .to_owned()
+                })
+            })
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            String::new()
+        } else {
+            format!(" — co-authored by {}", names.join(", "))
+        }
+    }
+}
+
+/// One bullet of the changelog: a pull request with its commits, or a lone commit.
+struct Entry<'a> {
+    section: usize,
+    pull_request: Option<&'a PullRequest>,
+    commits: Vec<&'a ChangelogCommit>,
+}
+
+impl Entry<'_> {
+    fn render(&self) -> String {
+        let Some(pull_request) = self.pull_request else {
+            let commit = self.commits[0];
+            return format!(
+                "- {} ({}){}",
+                commit.subject().describe(),
+                commit.link(),
+                commit.co_authors()
+            );
+        };
+        let title = Subject::parse(&pull_request.title).describe();
+        let pr_link = format!("[{}]({})", pull_request.reference, pull_request.url);
+        // squashed pull requests would only repeat their title
+        if let [commit] = self.commits.as_slice() {
+            return format!(
+                "- {title} ({pr_link}, {}){}",
+                commit.link(),
+                commit.co_authors()
+            );
+        }
+        let mut lines = vec![format!("- {title} ({pr_link})")];
+        for commit in &self.commits {
+            lines.push(format!(
+                "  - {} ({}){}",
+                commit.subject().describe(),
+                commit.link(),
+                commit.co_authors()
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Markdown changelog of nightly commits, grouped by pull request and commit type.
+pub fn nightly_changelog(commits: &[ChangelogCommit], compare_url: &str) -> String {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut by_pull_request: HashMap<&str, usize> = HashMap::new();
+    for commit in commits {
+        match &commit.pull_request {
+            Some(pull_request) => match by_pull_request.get(pull_request.reference.as_str()) {
+                Some(&index) => entries[index].commits.push(commit),
+                None => {
+                    let title = Subject::parse(&pull_request.title);
+                    // untyped pull request titles fall back to their first commit
+                    let section = match title.kind {
+                        Some(_) => title.section(),
+                        None => commit.subject().section(),
+                    };
+                    by_pull_request.insert(&pull_request.reference, entries.len());
+                    entries.push(Entry {
+                        section,
+                        pull_request: Some(pull_request),
+                        commits: vec![commit],
+                    });
+                }
+            },
+            None => entries.push(Entry {
+                section: commit.subject().section(),
+                pull_request: None,
+                commits: vec![commit],
+            }),
+        }
+    }
+
+    let mut changelog = String::from("# Changelog\n");
+    for (section, title) in SECTION_TITLES.iter().enumerate() {
+        let lines = entries
+            .iter()
+            .filter(|entry| entry.section == section)
+            .map(Entry::render)
+            .collect::<Vec<_>>();
+        if !lines.is_empty() {
+            changelog += &format!("\n## {title}\n\n{}\n", lines.join("\n"));
+        }
+    }
+    if entries.is_empty() {
+        changelog += "\nNo changes.\n";
+    }
+    changelog += &format!("\nFull changelog: {compare_url}");
+    changelog
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPARE_URL: &str = "https://github.com/o/r/compare/a...b";
+
+    fn commit(sha: &str, parents: &[&str], message: &str) -> ComparedCommit {
+        ComparedCommit {
+            sha: sha.to_owned(),
+            parents: parents.iter().map(|parent| (*parent).to_owned()).collect(),
+            message: message.to_owned(),
+            url: Url::parse(&format!("https://github.com/o/r/commit/{sha}")).unwrap(),
+        }
+    }
+
+    fn attribute(commits: Vec<ComparedCommit>) -> Vec<ChangelogCommit> {
+        attribute_pull_requests(commits, |number| {
+            Url::parse(&format!("https://github.com/o/r/pull/{number}")).ok()
+        })
+    }
+
+    fn references(commits: &[ChangelogCommit]) -> Vec<(&str, Option<&str>)> {
+        commits
+            .iter()
+            .map(|commit| {
+                let reference = commit.pull_request.as_ref();
+                (
+                    commit.sha.as_str(),
+                    reference.map(|pr| pr.reference.as_str()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn squash_suffix_names_the_pull_request() {
+        let commits = attribute(vec![
+            commit("s1", &["base"], "fix: crash on start (#8)"),
+            commit("c1", &["s1"], "tweak (not a pull request)"),
+        ]);
+        assert_eq!(references(&commits), [("s1", Some("#8")), ("c1", None)]);
+        let pull_request = commits[0].pull_request.as_ref().unwrap();
+        assert_eq!(pull_request.title, "fix: crash on start");
+        assert_eq!(pull_request.url.as_str(), "https://github.com/o/r/pull/8");
+    }
+
+    #[test]
+    fn github_merge_claims_its_branch() {
+        let commits = attribute(vec![
+            commit("c0", &["base"], "docs: readme"),
+            commit("b1", &["base"], "feat: part one"),
+            commit("b2", &["b1"], "fix typo"),
+            commit(
+                "m1",
+                &["c0", "b2"],
+                "Merge pull request #7 from a/b\n\nfeat(ui): big feature",
+            ),
+            commit("c1", &["m1"], "random tweak"),
+        ]);
+        // the merge commit itself is left out
+        assert_eq!(
+            references(&commits),
+            [
+                ("c0", None),
+                ("b1", Some("#7")),
+                ("b2", Some("#7")),
+                ("c1", None)
+            ]
+        );
+        assert_eq!(
+            commits[1].pull_request.as_ref().unwrap().title,
+            "feat(ui): big feature"
+        );
+    }
+
+    #[test]
+    fn gitlab_merge_claims_its_branch() {
+        let commits = attribute(vec![
+            commit("b1", &["base"], "feat: part one"),
+            commit(
+                "m1",
+                &["base", "b1"],
+                "Merge branch 'feature' into 'develop'\n\nfeat: big feature\n\nSee merge request group/project!12",
+            ),
+        ]);
+        assert_eq!(references(&commits), [("b1", Some("!12"))]);
+        assert_eq!(
+            commits[0].pull_request.as_ref().unwrap().title,
+            "feat: big feature"
+        );
+    }
+
+    #[test]
+    fn unrelated_merges_are_ignored() {
+        let commits = attribute(vec![
+            commit("b1", &["base"], "feat: part one"),
+            commit("m1", &["base", "b1"], "Merge branch 'develop' into feature"),
+        ]);
+        assert_eq!(references(&commits), [("b1", None)]);
+    }
+
+    #[test]
+    fn changelog_groups_pull_requests_and_credits_co_authors() {
+        let commits = attribute(vec![
+            commit("c0aaaaaaa", &["base"], "docs: readme"),
+            commit(
+                "b1aaaaaaa",
+                &["base"],
+                "feat: part one\n\nCo-authored-by: Alice <alice@example.com>",
+            ),
+            commit("b2aaaaaaa", &["b1aaaaaaa"], "fix typo"),
+            commit(
+                "m1aaaaaaa",
+                &["c0aaaaaaa", "b2aaaaaaa"],
+                "Merge pull request #7 from a/b\n\nfeat(ui): big feature",
+            ),
+            commit("s1aaaaaaa", &["m1aaaaaaa"], "fix: crash on start (#8)"),
+            commit("c1aaaaaaa", &["s1aaaaaaa"], "random tweak"),
+        ]);
+        assert_eq!(
+            nightly_changelog(&commits, COMPARE_URL),
+            "# Changelog
+
+## Features
+
+- **ui:** big feature ([#7](https://github.com/o/r/pull/7))
+  - part one ([b1aaaaa](https://github.com/o/r/commit/b1aaaaaaa)) — co-authored by Alice
+  - fix typo ([b2aaaaa](https://github.com/o/r/commit/b2aaaaaaa))
+
+## Fixes
+
+- crash on start ([#8](https://github.com/o/r/pull/8), [s1aaaaa](https://github.com/o/r/commit/s1aaaaaaa))
+
+## Chores
+
+- readme ([c0aaaaa](https://github.com/o/r/commit/c0aaaaaaa))
+
+## Other changes
+
+- random tweak ([c1aaaaa](https://github.com/o/r/commit/c1aaaaaaa))
+
+Full changelog: https://github.com/o/r/compare/a...b"
+        );
+    }
+
+    #[test]
+    fn empty_changelog() {
+        assert_eq!(
+            nightly_changelog(&[], COMPARE_URL),
+            format!("# Changelog\n\nNo changes.\n\nFull changelog: {COMPARE_URL}")
+        );
+    }
+}
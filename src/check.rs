@@ -0,0 +1,56 @@
+use clap::ValueEnum;
+use serde_json::json;
+
+use crate::cli::CommandArgs;
+use crate::source::{PrismArtifact, PrismRelease, ReleaseType, channel};
+
+fn artifact_json(artifact: &PrismArtifact) -> serde_json::Value {
+    json!({
+        "name": artifact.name,
+        "size": artifact.size_in_bytes,
+        "url": artifact.download_url,
+    })
+}
+
+/// Prints the outcome of `check`, `artifacts` holds the selected artifact first.
+pub fn print_check(
+    cfg: &CommandArgs,
+    release: &PrismRelease,
+    artifacts: &[&PrismArtifact],
+    changelog: Option<&str>,
+    update_available: bool,
+    json: bool,
+) -> eyre::Result<()> {
+    if json {
+        let channel = channel(cfg);
+        let current_version = match channel {
+            ReleaseType::Nightly => cfg.git_commit.as_deref(),
+            ReleaseType::Stable | ReleaseType::Beta => cfg.prism_version.as_deref(),
+        };
+        let channel = channel
+            .to_possible_value()
+            .map(|value| value.get_name().to_owned());
+        let document = json!({
+            "update_available": update_available,
+            "current_version": current_version,
+            "target_version": release.tag,
+            "channel": channel,
+            "name": release.name,
+            "date": release.created_at,
+            "prerelease": release.prerelease,
+            "artifact": artifacts.first().map(|artifact| artifact_json(artifact)),
+            "candidates": artifacts.iter().skip(1).map(|artifact| artifact_json(artifact)).collect::<Vec<_>>(),
+            "changelog": changelog,
+        });
+        println!("{}", serde_json::to_string_pretty(&document)?);
+        return Ok(());
+    }
+
+    if update_available {
+        println!("Name: {}", release.name);
+        println!("Version: {}", release.tag);
+        println!("TimeStamp: {}", release.created_at.format("%+"));
+        println!("{}", changelog.unwrap_or_default());
+    }
+    Ok(())
+}
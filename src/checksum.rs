@@ -0,0 +1,124 @@
+use crate::download::fetch_bytes;
+use crate::source::{PrismArtifact, PrismRelease};
+
+/// Combined checksum files, in the `sha256sum` output format.
+const SUMS_FILES: [&str; 2] = ["sha256sums.txt", "sha256sums"];
+const COMPANION_EXTENSION: &str = ".sha256";
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Whether `name` is a checksum file published next to the real assets.
+pub fn is_checksum_file(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SUMS_FILES.contains(&name.as_str()) || name.ends_with(COMPANION_EXTENSION)
+}
+
+/// Finds the digest of `name` in `sha256sum` output, or takes the only one of a `.sha256` file.
+fn parse_checksums(text: &str, name: &str) -> Option<String> {
+    let entries: Vec<(&str, Option<&str>)> = text
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let digest = parts.next()?;
+            // binary mode entries are prefixed with a star
+            let file = parts.next().map(|file| file.trim_start_matches('*'));
+            Some((digest, file))
+        })
+        .collect();
+    let digest = match entries.as_slice() {
+        [(digest, None)] => Some(*digest),
+        _ => entries
+            .iter()
+            .find(|(_, file)| file.is_some_and(|file| file.rsplit('/').next() == Some(name)))
+            .map(|(digest, _)| *digest),
+    }?;
+    let valid = digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit());
+    valid.then(|| digest.to_lowercase())
+}
+
+/// SHA-256 the downloaded `artifact` must have, from the source or a checksum asset.
+pub async fn expected_sha256(
+    release: &PrismRelease,
+    artifact: &PrismArtifact,
+    token: Option<&str>,
+) -> eyre::Result<Option<String>> {
+    if let Some(digest) = &artifact.sha256 {
+        return Ok(Some(digest.to_lowercase()));
+    }
+    let companion = format!("{}{COMPANION_EXTENSION}", artifact.name).to_lowercase();
+    let checksum_assets = release.assets.iter().filter(|asset| {
+        let name = asset.name.to_lowercase();
+        name == companion || SUMS_FILES.contains(&name.as_str())
+    });
+    for asset in checksum_assets {
+        let text = String::from_utf8(fetch_bytes(asset, token).await?)?;
+        match parse_checksums(&text, &artifact.name) {
+            Some(digest) => {
+                log::info!(
+                    "Using the checksum of {} from {}",
+                    artifact.name,
+                    asset.name
+                );
+                return Ok(Some(digest));
+            }
+            None => log::warn!("{} has no checksum for {}", asset.name, artifact.name),
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTIFACT: &str = "PrismLauncher-Linux-Qt6-Portable-9.2.tar.gz";
+    const DIGEST: &str = "0a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f9";
+    const OTHER: &str = "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff";
+
+    #[test]
+    fn takes_a_bare_digest() {
+        assert_eq!(
+            parse_checksums(&format!("{}\n", DIGEST.to_uppercase()), ARTIFACT).as_deref(),
+            Some(DIGEST)
+        );
+    }
+
+    #[test]
+    fn finds_the_entry_of_the_artifact() {
+        let text = format!("{OTHER}  PrismLauncher-Windows.zip\n{DIGEST}  {ARTIFACT}\n");
+        assert_eq!(parse_checksums(&text, ARTIFACT).as_deref(), Some(DIGEST));
+    }
+
+    #[test]
+    fn accepts_binary_mode_entries() {
+        let text = format!("{OTHER} *PrismLauncher-Windows.zip\n{DIGEST} *{ARTIFACT}\n");
+        assert_eq!(parse_checksums(&text, ARTIFACT).as_deref(), Some(DIGEST));
+    }
+
+    #[test]
+    fn accepts_path_prefixed_names() {
+        let text =
+            format!("{OTHER}  ./dist/PrismLauncher-Windows.zip\n{DIGEST}  ./dist/{ARTIFACT}\n");
+        assert_eq!(parse_checksums(&text, ARTIFACT).as_deref(), Some(DIGEST));
+    }
+
+    #[test]
+    fn misses_absent_entries() {
+        let text = format!("{OTHER}  PrismLauncher-Windows.zip\n{OTHER}  {ARTIFACT}.minisig\n");
+        assert_eq!(parse_checksums(&text, ARTIFACT), None);
+        // a lone entry must still be for the artifact
+        let text = format!("{DIGEST}  PrismLauncher-Windows.zip\n");
+        assert_eq!(parse_checksums(&text, ARTIFACT), None);
+    }
+
+    #[test]
+    fn rejects_invalid_digests() {
+        assert_eq!(
+            parse_checksums(&format!("{}  {ARTIFACT}\n", &DIGEST[1..]), ARTIFACT),
+            None
+        );
+        assert_eq!(parse_checksums("not-a-digest\n", ARTIFACT), None);
+    }
+}
@@ -1,12 +1,30 @@
 use clap::{Parser, Subcommand};
+use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
+use url::Url;
 
-use crate::github;
+use crate::source;
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
-    Check,
+    /// Exits with 100 when an update is available, see the Readme for every exit code
+    Check {
+        #[arg(long, help = "Print a JSON document instead of text")]
+        json: bool,
+    },
     Update,
+    /// Browse the available releases (or nightly runs)
+    List {
+        #[arg(long, help = "Page to show, starting at 1", default_value = "1")]
+        page: u32,
+
+        #[arg(long, help = "Entries per page", default_value = "10")]
+        per_page: u32,
+
+        #[arg(long, help = "Print JSON instead of a table")]
+        json: bool,
+    },
 }
 
 #[derive(Parser, Debug)]
@@ -36,10 +54,55 @@ pub struct CommandArgs {
     )]
     pub tmp_path: Option<PathBuf>,
 
-    // github
     #[arg(
         long,
-        help = "GitHub repository owner name",
+        help = "Directory caching API responses [default: <root directory>/cache]",
+        value_name = "cache directory"
+    )]
+    pub cache_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "How long cached API responses are used without asking the server again, entries unused for 24 times as long (at least a day) are deleted",
+        value_name = "duration",
+        value_parser = humantime::parse_duration,
+        default_value = "5m"
+    )]
+    pub cache_max_age: Duration,
+
+    // source
+    #[arg(
+        long,
+        help = "Where releases are published",
+        value_name = "source",
+        default_value = "github"
+    )]
+    pub source: source::SourceType,
+
+    #[arg(
+        long,
+        help = "URL of the release source: the Gitea/Forgejo or GitLab server, the feed/appcast, or a file:// directory",
+        value_name = "url"
+    )]
+    pub source_url: Option<Url>,
+
+    #[arg(
+        long,
+        help = "Directory of the local source, laid out as <tag>/<assets>",
+        value_name = "directory"
+    )]
+    pub source_path: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Operating system version, checked against the appcast minimumSystemVersion",
+        value_name = "version"
+    )]
+    pub system_version: Option<String>,
+
+    #[arg(
+        long,
+        help = "Repository owner name",
         value_name = "repo owner",
         default_value = "PrismLauncher"
     )]
@@ -47,12 +110,74 @@ pub struct CommandArgs {
 
     #[arg(
         long,
-        help = "GitHub repository name",
+        help = "Repository name",
         value_name = "repo name",
         default_value = "PrismLauncher"
     )]
     pub repo_name: String,
 
+    // github
+    #[arg(
+        long,
+        help = "GitHub API base URL, for GitHub Enterprise or a local mock server",
+        value_name = "url",
+        default_value = "https://api.github.com"
+    )]
+    pub github_api_url: Url,
+
+    #[arg(
+        long,
+        help = "Host serving the nightly workflow artifacts",
+        value_name = "url",
+        default_value = "https://nightly.link"
+    )]
+    pub artifact_host: Url,
+
+    #[arg(
+        long,
+        help = "Proxy url of nightly artifacts, used when no token allows the API download. Placeholders: {host}, {owner}, {repo}, {id}",
+        value_name = "template",
+        default_value = "{host}/{owner}/{repo}/actions/artifacts/{id}.zip"
+    )]
+    pub artifact_url_template: String,
+
+    #[arg(
+        long = "mirror",
+        help = "Mirror tried when a download fails, repeat for more. Placeholders: {url}, {host}, {path}, {tag}, {name}",
+        value_name = "template"
+    )]
+    pub mirrors: Vec<String>,
+
+    #[arg(
+        long = "public-key",
+        help = "Minisign or base64 ed25519 public key artifacts must be signed with, repeat for more. Updates are refused unless a key is configured here or in the build, or --no-verify-signature is passed",
+        value_name = "key"
+    )]
+    pub public_keys: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Install artifacts even when no public key is configured or their signature is missing or invalid"
+    )]
+    pub no_verify_signature: bool,
+
+    #[arg(
+        long,
+        help = "GitHub token used for API calls and private asset downloads",
+        value_name = "token",
+        env = "DISPERSION_GITHUB_TOKEN",
+        hide_env_values = true
+    )]
+    pub github_token: Option<String>,
+
+    #[arg(
+        long,
+        help = "File containing the GitHub token",
+        value_name = "token file",
+        conflicts_with = "github_token"
+    )]
+    pub github_token_file: Option<PathBuf>,
+
     // github action
     #[arg(
         long,
@@ -70,6 +195,13 @@ pub struct CommandArgs {
     )]
     pub branch: String,
 
+    #[arg(
+        long,
+        help = "Only consider workflow runs triggered by this event (e.g. push, schedule)",
+        value_name = "event"
+    )]
+    pub workflow_event: Option<String>,
+
     // version
     #[arg(
         long,
@@ -95,6 +227,13 @@ pub struct CommandArgs {
     )]
     pub build_artifact: Option<String>,
 
+    #[arg(
+        long,
+        help = "Install this exact release tag, even if it is older than the installed one",
+        value_name = "tag"
+    )]
+    pub target_version: Option<String>,
+
     #[arg(long, help = "The app binary name", value_name = "binary name")]
     pub app_name: Option<String>,
 
@@ -105,7 +244,7 @@ pub struct CommandArgs {
         value_name = "type",
         default_value = "nightly"
     )]
-    pub release_type: github::ReleaseType,
+    pub release_type: source::ReleaseType,
 
     #[arg(
         short = 'f',
@@ -114,7 +253,10 @@ pub struct CommandArgs {
     )]
     pub force: bool,
 
-    #[arg(long, help = "Should log be printed on std_out")]
+    #[arg(
+        long,
+        help = "Should log be printed on std_out (std_err with `check --json` and `list --json`)"
+    )]
     pub log_stdout: bool,
 
     #[arg(
@@ -127,3 +269,19 @@ pub struct CommandArgs {
     #[command(subcommand)]
     pub command: Commands,
 }
+
+impl CommandArgs {
+    /// The GitHub token from `--github-token`, its env var or `--github-token-file`.
+    pub fn github_token(&self) -> eyre::Result<Option<String>> {
+        let token = match (&self.github_token, &self.github_token_file) {
+            (Some(token), _) => token.clone(),
+            (None, Some(path)) => fs::read_to_string(path)?,
+            (None, None) => return Ok(None),
+        };
+        let token = token.trim();
+        if token.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(token.to_string()))
+    }
+}
@@ -1,15 +1,234 @@
 use indicatif::{ProgressBar, ProgressState, ProgressStyle};
-use reqwest::Client;
-use reqwest::header::CONTENT_DISPOSITION;
-use std::fs::File;
-use std::io::Write;
-use std::path::{Path, PathBuf};
-
-pub async fn fetch_url(url: url::Url, path: &Path, size: usize) -> eyre::Result<PathBuf> {
-    let client = Client::new();
-    let response = client.get(url).send().await?;
+use reqwest::header::{ACCEPT, CONTENT_DISPOSITION, LOCATION};
+use reqwest::{Client, Response, redirect};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+use crate::checksum::to_hex;
+use crate::source::PrismArtifact;
+
+/// Redirects followed by hand on authenticated downloads.
+const MAX_REDIRECTS: usize = 10;
+
+/// GETs an API url with the token, then follows the redirects to the storage host without it.
+async fn send_authenticated(url: url::Url, token: &str) -> eyre::Result<Response> {
+    let client = Client::builder()
+        .redirect(redirect::Policy::none())
+        .build()?;
+    // asset API urls only return the file itself when asked for raw bytes
+    let mut response = client
+        .get(url)
+        .bearer_auth(token)
+        .header(ACCEPT, "application/octet-stream")
+        .send()
+        .await?;
+    for _ in 0..MAX_REDIRECTS {
+        if !response.status().is_redirection() {
+            return Ok(response);
+        }
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|val| val.to_str().ok())
+            .ok_or_else(|| eyre::eyre!("Redirect from {} without a location", response.url()))?;
+        let next = response.url().join(location)?;
+        log::debug!(
+            "Following redirect to {}",
+            next.host_str().unwrap_or_default()
+        );
+        // the signed storage url must not receive the token
+        response = client.get(next).send().await?;
+    }
+    Err(eyre::eyre!("Too many redirects"))
+}
+
+/// Downloads the artifact through the API when a token is available, falling back to its
+/// public url and then to its mirrors. A download not matching `sha256` counts as failed.
+pub async fn fetch_artifact(
+    artifact: &PrismArtifact,
+    path: &Path,
+    token: Option<&str>,
+    sha256: Option<&str>,
+) -> eyre::Result<PathBuf> {
+    if let (Some(api_url), Some(token)) = (&artifact.api_url, token) {
+        match fetch_url(
+            api_url.clone(),
+            path,
+            artifact.size_in_bytes,
+            Some(token),
+            sha256,
+        )
+        .await
+        {
+            Ok(v) => {
+                log::info!("Downloaded {} through the API", artifact.name);
+                return Ok(v);
+            }
+            Err(err) => log::warn!(
+                "Failed to download {} through the API, falling back to {}: {:?}",
+                artifact.name,
+                artifact.download_url,
+                err
+            ),
+        }
+    }
+    let mut last_error = None;
+    for url in std::iter::once(&artifact.download_url).chain(&artifact.mirrors) {
+        match fetch_url(url.clone(), path, artifact.size_in_bytes, None, sha256).await {
+            Ok(v) => {
+                log::info!("Downloaded {} from {}", artifact.name, url);
+                return Ok(v);
+            }
+            Err(err) => {
+                log::warn!(
+                    "Failed to download {} from {}: {:?}",
+                    artifact.name,
+                    url,
+                    err
+                );
+                last_error = Some(err);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| eyre::eyre!("No url to download {} from", artifact.name)))
+}
+
+async fn fetch_url_bytes(url: &url::Url, token: Option<&str>) -> eyre::Result<Vec<u8>> {
+    if url.scheme() == "file" {
+        let path = url
+            .to_file_path()
+            .map_err(|_| eyre::eyre!("Invalid file url {url}"))?;
+        return Ok(fs::read(path)?);
+    }
+    let response = match token {
+        Some(token) => send_authenticated(url.clone(), token).await?,
+        None => Client::new().get(url.clone()).send().await?,
+    };
+    Ok(response.error_for_status()?.bytes().await?.to_vec())
+}
+
+/// Reads a small companion asset, such as a checksum or signature file, into memory.
+/// Like the artifact itself it goes through the API when a token is available, then its public
+/// url and its mirrors.
+pub async fn fetch_bytes(asset: &PrismArtifact, token: Option<&str>) -> eyre::Result<Vec<u8>> {
+    if let (Some(api_url), Some(token)) = (&asset.api_url, token) {
+        match fetch_url_bytes(api_url, Some(token)).await {
+            Ok(v) => return Ok(v),
+            Err(err) => log::warn!(
+                "Failed to download {} through the API, falling back to {}: {:?}",
+                asset.name,
+                asset.download_url,
+                err
+            ),
+        }
+    }
+    let mut last_error = None;
+    for url in std::iter::once(&asset.download_url).chain(&asset.mirrors) {
+        match fetch_url_bytes(url, None).await {
+            Ok(v) => return Ok(v),
+            Err(err) => {
+                log::warn!("Failed to download {} from {}: {:?}", asset.name, url, err);
+                last_error = Some(err);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| eyre::eyre!("No url to download {} from", asset.name)))
+}
+
+/// Deletes a bad download so that it can not be unpacked by mistake.
+fn discard(path_to_file: &Path, err: eyre::Report) -> eyre::Report {
+    if let Err(remove_err) = fs::remove_file(path_to_file) {
+        log::warn!("Failed to remove {:?}: {:?}", path_to_file, remove_err);
+    }
+    err
+}
+
+/// Removes the file and fails when its digest is not the expected one.
+fn verify_sha256(path_to_file: &Path, actual: &str, expected: Option<&str>) -> eyre::Result<()> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(discard(
+            path_to_file,
+            eyre::eyre!(
+                "Checksum mismatch for {:?}: expected {expected}, got {actual}",
+                path_to_file
+            ),
+        ));
+    }
+    log::info!("Checksum of {:?} verified", path_to_file);
+    Ok(())
+}
+
+/// Releases of the local source are copied instead of downloaded.
+fn copy_file(
+    url: &url::Url,
+    path: &Path,
+    size: usize,
+    sha256: Option<&str>,
+) -> eyre::Result<PathBuf> {
+    let source = url
+        .to_file_path()
+        .map_err(|_| eyre::eyre!("Invalid file url {url}"))?;
+    let filename = source
+        .file_name()
+        .ok_or_else(|| eyre::eyre!("No file name in {url}"))?;
+    let path_to_file = path.join(filename);
+    let copied = fs::copy(&source, &path_to_file)?;
+    if size != 0 && copied != size as u64 {
+        return Err(discard(
+            &path_to_file,
+            eyre::eyre!("Copied {copied} bytes from {url}, expected {size}"),
+        ));
+    }
+    log::info!("File copied successfully.");
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(&path_to_file)?, &mut hasher)?;
+    verify_sha256(&path_to_file, &to_hex(&hasher.finalize()), sha256)?;
+    Ok(path_to_file)
+}
+
+/// File name suggested by the server, mirrors included, which must stay in the download directory.
+fn safe_file_name(name: &str) -> eyre::Result<&str> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(file_name)), None) => file_name
+            .to_str()
+            .ok_or_else(|| eyre::eyre!("Invalid file name {name:?}")),
+        _ => Err(eyre::eyre!("Refusing to save a download as {name:?}")),
+    }
+}
+
+async fn fetch_url(
+    url: url::Url,
+    path: &Path,
+    size: usize,
+    token: Option<&str>,
+    sha256: Option<&str>,
+) -> eyre::Result<PathBuf> {
+    if url.scheme() == "file" {
+        return copy_file(&url, path, size, sha256);
+    }
+    let response = match token {
+        Some(token) => send_authenticated(url, token).await?,
+        None => Client::new().get(url).send().await?,
+    };
     let mut response = response.error_for_status()?;
-    let bar = ProgressBar::new(size.try_into().unwrap());
+    // a size of 0 means the source does not know it
+    let expected_size = match (size as u64, response.content_length()) {
+        (0, content_length) => content_length,
+        (size, Some(content_length)) if content_length != size => {
+            return Err(eyre::eyre!(
+                "{} announces {content_length} bytes, expected {size}",
+                response.url()
+            ));
+        }
+        (size, _) => Some(size),
+    };
+    let bar = ProgressBar::new(expected_size.unwrap_or_default());
     bar.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})")
     .unwrap()
     .with_key("eta", |state: &ProgressState, w: &mut dyn std::fmt::Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
@@ -21,20 +240,52 @@ pub async fn fetch_url(url: url::Url, path: &Path, size: usize) -> eyre::Result<
         .and_then(|cd| cd.split("filename=").nth(1))
         .map(|name| name.trim_matches('"'))
         .map(String::from)
+        // mirrors and plain web servers often only name the file in the url
+        .or_else(|| {
+            response
+                .url()
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+        })
         .unwrap_or_else(|| "downloaded_file".to_string());
 
-    let path_to_file = path.join(filename);
+    let path_to_file = path.join(safe_file_name(&filename)?);
     // Open a file to write the stream to
     let mut file = File::create(&path_to_file)?;
+    let mut hasher = Sha256::new();
+    let mut received: u64 = 0;
     // Stream the response body and write it to the file chunk by chunk
     while let Some(chunk) = response.chunk().await? {
         let s = chunk.len();
+        received += s as u64;
+        if let Some(expected) = expected_size
+            && received > expected
+        {
+            drop(file);
+            return Err(discard(
+                &path_to_file,
+                eyre::eyre!("Download runs past the expected {expected} bytes"),
+            ));
+        }
         file.write_all(&chunk)?;
+        hasher.update(&chunk);
         bar.inc(s.try_into().unwrap());
     }
 
     file.flush()?;
+    drop(file);
     bar.finish_and_clear();
+    if let Some(expected) = expected_size
+        && received != expected
+    {
+        return Err(discard(
+            &path_to_file,
+            eyre::eyre!("Download ended after {received} of {expected} bytes"),
+        ));
+    }
     log::info!("File downloaded successfully.");
+    verify_sha256(&path_to_file, &to_hex(&hasher.finalize()), sha256)?;
     Ok(path_to_file)
 }
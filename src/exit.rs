@@ -0,0 +1,75 @@
+use std::fmt;
+use std::process::ExitCode;
+
+use crate::github::RateLimitExhausted;
+
+/// Exit statuses documented in the Readme, launchers rely on them so keep them stable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    /// Already up to date, or the update was installed.
+    Success,
+    /// Any error without a more specific status, bad arguments included.
+    Failure,
+    /// The release source could not be queried.
+    SourceUnavailable,
+    /// The release has no artifact for this installation.
+    NoMatchingArtifact,
+    DownloadFailed,
+    /// Unpacking, backing up or installing the update failed.
+    InstallFailed,
+    /// The artifact is not signed by a trusted key.
+    SignatureInvalid,
+    /// The GitHub rate limit did not reset in time (EX_TEMPFAIL).
+    RateLimited,
+    /// `check` found a newer release.
+    UpdateAvailable,
+}
+
+impl Status {
+    pub fn code(self) -> u8 {
+        match self {
+            Status::Success => 0,
+            Status::Failure => 1,
+            Status::SourceUnavailable => 3,
+            Status::NoMatchingArtifact => 4,
+            Status::DownloadFailed => 5,
+            Status::InstallFailed => 6,
+            Status::SignatureInvalid => 7,
+            Status::RateLimited => 75,
+            Status::UpdateAvailable => 100,
+        }
+    }
+
+    /// Status of a failed run, errors are tagged with `wrap_err(Status::...)`.
+    pub fn of_error(err: &eyre::Report) -> Self {
+        if err.chain().any(|cause| cause.is::<RateLimitExhausted>()) {
+            return Status::RateLimited;
+        }
+        err.downcast_ref::<Status>()
+            .copied()
+            .unwrap_or(Status::Failure)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            Status::Success => "Success",
+            Status::Failure => "Failure",
+            Status::SourceUnavailable => "Failed to query the release source",
+            Status::NoMatchingArtifact => "No artifact matches this installation",
+            Status::DownloadFailed => "Failed to download the update",
+            Status::InstallFailed => "Failed to install the update",
+            Status::SignatureInvalid => "The update is not signed by a trusted key",
+            Status::RateLimited => "GitHub rate limit exhausted",
+            Status::UpdateAvailable => "Update available",
+        };
+        f.write_str(description)
+    }
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        ExitCode::from(status.code())
+    }
+}
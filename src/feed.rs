@@ -0,0 +1,179 @@
+//! Self-hosted update feed: a static JSON or TOML manifest listing every release.
+//!
+//! ```json
+//! {
+//!   "releases": [{
+//!     "version": "9.2",
+//!     "channel": "stable",
+//!     "name": "Prism Launcher 9.2",
+//!     "date": "2025-01-01T00:00:00Z",
+//!     "notes": "Markdown release notes",
+//!     "assets": [{ "name": "PrismLauncher-Linux-Qt6-Portable-9.2.tar.gz", "url": "9.2/PrismLauncher-Linux-Qt6-Portable-9.2.tar.gz", "size": 123456, "sha256": "..." }]
+//!   }]
+//! }
+//! ```
+//!
+//! The TOML flavour uses the same keys. `date` is an RFC 3339 string in both, and asset
+//! urls may be relative to the manifest url. `sha256` is optional and checked after the download.
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use reqwest::header::CONTENT_TYPE;
+use serde::Deserialize;
+use std::cmp::Reverse;
+use url::Url;
+
+use crate::cli::CommandArgs;
+use crate::source::{PrismArtifact, PrismRelease, ReleaseSource, ReleaseType, paginate};
+use crate::system::newest_release;
+
+#[derive(Deserialize)]
+struct Feed {
+    releases: Vec<FeedRelease>,
+}
+
+#[derive(Deserialize)]
+struct FeedRelease {
+    version: String,
+    #[serde(default = "default_channel")]
+    channel: ReleaseType,
+    name: Option<String>,
+    date: DateTime<Utc>,
+    notes: Option<String>,
+    assets: Vec<FeedAsset>,
+}
+
+#[derive(Deserialize)]
+struct FeedAsset {
+    name: String,
+    url: String,
+    #[serde(default)]
+    size: usize,
+    sha256: Option<String>,
+}
+
+fn default_channel() -> ReleaseType {
+    ReleaseType::Stable
+}
+
+fn parse_feed(manifest_url: &Url, content_type: &str, text: &str) -> eyre::Result<Feed> {
+    let is_toml =
+        content_type.contains("toml") || manifest_url.path().to_lowercase().ends_with(".toml");
+    if is_toml {
+        Ok(toml::from_str(text)?)
+    } else {
+        Ok(serde_json::from_str(text)?)
+    }
+}
+
+impl FeedRelease {
+    fn into_release(self, manifest_url: &Url) -> eyre::Result<PrismRelease> {
+        let assets = self
+            .assets
+            .into_iter()
+            .map(|asset| {
+                Ok(PrismArtifact {
+                    download_url: manifest_url.join(&asset.url)?,
+                    name: asset.name,
+                    size_in_bytes: asset.size,
+                    api_url: None,
+                    mirrors: Vec::new(),
+                    sha256: asset.sha256,
+                })
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        Ok(PrismRelease {
+            name: self.name.unwrap_or_else(|| self.version.clone()),
+            tag: self.version,
+            created_at: self.date,
+            assets,
+            body: self.notes,
+            prerelease: self.channel == ReleaseType::Beta,
+        })
+    }
+}
+
+/// Releases listed in a static manifest hosted on any web server or CDN.
+pub struct FeedSource<'a> {
+    client: Client,
+    manifest_url: Url,
+    cfg: &'a CommandArgs,
+}
+
+impl<'a> FeedSource<'a> {
+    pub fn new(cfg: &'a CommandArgs) -> eyre::Result<Self> {
+        let manifest_url = cfg
+            .source_url
+            .clone()
+            .ok_or_else(|| eyre::eyre!("The feed source needs the manifest url in --source-url"))?;
+        Ok(Self {
+            client: Client::new(),
+            manifest_url,
+            cfg,
+        })
+    }
+
+    async fn get_feed(&self) -> eyre::Result<Feed> {
+        let response = self
+            .client
+            .get(self.manifest_url.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|val| val.to_str().ok())
+            .unwrap_or_default()
+            .to_lowercase();
+        let text = response.text().await?;
+        parse_feed(&self.manifest_url, &content_type, &text)
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for FeedSource<'_> {
+    async fn get_latest(&self) -> eyre::Result<PrismRelease> {
+        let feed = self.get_feed().await?;
+        let release_type = &self.cfg.release_type;
+        let releases = feed
+            .releases
+            .into_iter()
+            .filter(|release| release_type.includes(&release.channel));
+        let release = if *release_type == ReleaseType::Beta {
+            let releases = releases
+                .map(|release| release.into_release(&self.manifest_url))
+                .collect::<eyre::Result<Vec<_>>>()?;
+            newest_release(releases)
+        } else {
+            releases
+                .max_by_key(|release| release.date)
+                .map(|release| release.into_release(&self.manifest_url))
+                .transpose()?
+        };
+        release.ok_or_else(|| eyre::eyre!("No {release_type:?} release in {}", self.manifest_url))
+    }
+
+    async fn get_by_tag(&self, tag: &str) -> eyre::Result<PrismRelease> {
+        let feed = self.get_feed().await?;
+        feed.releases
+            .into_iter()
+            .find(|release| release.version == tag)
+            .ok_or_else(|| eyre::eyre!("No release {tag:?} in {}", self.manifest_url))?
+            .into_release(&self.manifest_url)
+    }
+
+    async fn list(&self, page: u32, per_page: u32) -> eyre::Result<Vec<PrismRelease>> {
+        let mut releases: Vec<FeedRelease> = self
+            .get_feed()
+            .await?
+            .releases
+            .into_iter()
+            .filter(|release| self.cfg.release_type.includes(&release.channel))
+            .collect();
+        releases.sort_by_key(|release| Reverse(release.date));
+        paginate(releases, page, per_page)
+            .map(|release| release.into_release(&self.manifest_url))
+            .collect()
+    }
+}
@@ -0,0 +1,282 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Deserialize;
+use url::Url;
+
+use crate::cli::CommandArgs;
+use crate::rest::{as_base, get_json};
+use crate::source::{PrismArtifact, PrismRelease, ReleaseSource, ReleaseType};
+use crate::system::newest_release;
+
+/// Instance used when `--source-url` is not given.
+const DEFAULT_INSTANCE: &str = "https://codeberg.org";
+
+#[derive(Deserialize)]
+struct Release {
+    name: String,
+    tag_name: String,
+    created_at: DateTime<Utc>,
+    body: Option<String>,
+    draft: bool,
+    prerelease: bool,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    size: usize,
+    browser_download_url: Url,
+}
+
+impl From<Release> for PrismRelease {
+    fn from(release: Release) -> Self {
+        PrismRelease {
+            name: release.name,
+            tag: release.tag_name,
+            created_at: release.created_at,
+            assets: release
+                .assets
+                .into_iter()
+                .map(|asset| PrismArtifact {
+                    name: asset.name,
+                    size_in_bytes: asset.size,
+                    api_url: None,
+                    mirrors: Vec::new(),
+                    sha256: None,
+                    download_url: asset.browser_download_url,
+                })
+                .collect(),
+            body: release.body.filter(|body| !body.is_empty()),
+            prerelease: release.prerelease,
+        }
+    }
+}
+
+/// Stable (and beta) releases of a Gitea or Forgejo instance (Codeberg included).
+pub struct GiteaReleaseSource<'a> {
+    client: Client,
+    api_url: Url,
+    cfg: &'a CommandArgs,
+}
+
+impl<'a> GiteaReleaseSource<'a> {
+    pub fn new(cfg: &'a CommandArgs) -> eyre::Result<Self> {
+        let instance = match &cfg.source_url {
+            Some(url) => url.clone(),
+            None => DEFAULT_INSTANCE.parse()?,
+        };
+        Ok(Self {
+            client: Client::new(),
+            api_url: as_base(&instance).join("api/v1/")?,
+            cfg,
+        })
+    }
+
+    fn repo_url(&self, path: &str) -> eyre::Result<Url> {
+        Ok(self.api_url.join(&format!(
+            "repos/{}/{}/{path}",
+            self.cfg.repo_owner, self.cfg.repo_name
+        ))?)
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for GiteaReleaseSource<'_> {
+    async fn get_latest(&self) -> eyre::Result<PrismRelease> {
+        if self.cfg.release_type == ReleaseType::Beta {
+            let releases: Vec<Release> =
+                get_json(&self.client, self.cfg, self.repo_url("releases?limit=50")?).await?;
+            let releases = releases
+                .into_iter()
+                .filter(|release| !release.draft)
+                .map(PrismRelease::from)
+                .collect();
+            return newest_release(releases).ok_or_else(|| eyre::eyre!("No release found"));
+        }
+        // drafts and prereleases are already skipped by the latest endpoint
+        let release: Release =
+            get_json(&self.client, self.cfg, self.repo_url("releases/latest")?).await?;
+        Ok(release.into())
+    }
+
+    async fn get_by_tag(&self, tag: &str) -> eyre::Result<PrismRelease> {
+        let mut url = self.repo_url("releases/tags/")?;
+        // tags may contain `/`, `?` or `#`, they must stay a single path segment
+        url.path_segments_mut()
+            .map_err(|_| eyre::eyre!("The Gitea API url can not be a base"))?
+            .pop_if_empty()
+            .push(tag);
+        let release: Release = get_json(&self.client, self.cfg, url).await?;
+        Ok(release.into())
+    }
+
+    async fn list(&self, page: u32, per_page: u32) -> eyre::Result<Vec<PrismRelease>> {
+        let url = self.repo_url(&format!("releases?page={page}&limit={per_page}"))?;
+        let releases: Vec<Release> = get_json(&self.client, self.cfg, url).await?;
+        Ok(releases
+            .into_iter()
+            .filter(|release| !release.draft)
+            .map(PrismRelease::from)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    const RELEASE_9_2: &str = r#"{
+        "name": "Prism 9.2",
+        "tag_name": "9.2",
+        "created_at": "2025-02-01T00:00:00Z",
+        "body": "",
+        "draft": false,
+        "prerelease": false,
+        "assets": [{
+            "name": "PrismLauncher-Linux-Qt6-Portable-9.2.tar.gz",
+            "size": 5,
+            "browser_download_url": "https://codeberg.org/o/r/releases/download/9.2/a.tar.gz"
+        }]
+    }"#;
+
+    /// Answers requests with canned JSON bodies by path and query, recording what was asked.
+    fn stub(routes: Vec<(&'static str, String)>) -> (Url, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+                // the headers are not needed, only skipped
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+                let (status, body) = match routes.iter().find(|(route, _)| *route == path) {
+                    Some((_, body)) => ("200 OK", body.as_str()),
+                    None => ("404 Not Found", "{}"),
+                };
+                seen.lock().unwrap().push(path.to_owned());
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn args(instance: &Url, release_type: &str) -> CommandArgs {
+        CommandArgs::parse_from([
+            "dispersion",
+            "--source",
+            "gitea",
+            "--source-url",
+            instance.as_str(),
+            "--repo-owner",
+            "o",
+            "--repo-name",
+            "r",
+            "--release-type",
+            release_type,
+            "--prism-version",
+            "9.0",
+            "--git-commit",
+            "abc",
+            "--build-artifact",
+            "Linux-Qt6",
+            "check",
+        ])
+    }
+
+    #[tokio::test]
+    async fn latest_release_is_mapped() {
+        let (instance, requests) = stub(vec![(
+            "/api/v1/repos/o/r/releases/latest",
+            RELEASE_9_2.to_owned(),
+        )]);
+        let cfg = args(&instance, "stable");
+        let release = GiteaReleaseSource::new(&cfg)
+            .unwrap()
+            .get_latest()
+            .await
+            .unwrap();
+        assert_eq!(release.tag, "9.2");
+        assert_eq!(release.name, "Prism 9.2");
+        assert_eq!(release.body, None);
+        assert!(!release.prerelease);
+        let [asset] = release.assets.as_slice() else {
+            panic!("expected one asset");
+        };
+        assert_eq!(asset.name, "PrismLauncher-Linux-Qt6-Portable-9.2.tar.gz");
+        assert_eq!(asset.size_in_bytes, 5);
+        assert_eq!(
+            asset.download_url.as_str(),
+            "https://codeberg.org/o/r/releases/download/9.2/a.tar.gz"
+        );
+        assert_eq!(
+            *requests.lock().unwrap(),
+            ["/api/v1/repos/o/r/releases/latest"]
+        );
+    }
+
+    #[tokio::test]
+    async fn beta_skips_drafts_and_takes_the_newest_version() {
+        let release = |tag: &str, draft: bool, prerelease: bool| {
+            RELEASE_9_2
+                .replace(r#""tag_name": "9.2""#, &format!(r#""tag_name": "{tag}""#))
+                .replace(r#""draft": false"#, &format!(r#""draft": {draft}"#))
+                .replace(
+                    r#""prerelease": false"#,
+                    &format!(r#""prerelease": {prerelease}"#),
+                )
+        };
+        let listing = format!(
+            "[{}, {}, {}]",
+            release("9.2", false, false),
+            release("9.4", true, false),
+            release("9.3-rc1", false, true)
+        );
+        let (instance, _) = stub(vec![("/api/v1/repos/o/r/releases?limit=50", listing)]);
+        let cfg = args(&instance, "beta");
+        let release = GiteaReleaseSource::new(&cfg)
+            .unwrap()
+            .get_latest()
+            .await
+            .unwrap();
+        assert_eq!(release.tag, "9.3-rc1");
+        assert!(release.prerelease);
+    }
+
+    #[tokio::test]
+    async fn tags_are_escaped() {
+        let (instance, requests) = stub(vec![(
+            "/api/v1/repos/o/r/releases/tags/release%2F9.2",
+            RELEASE_9_2.to_owned(),
+        )]);
+        let cfg = args(&instance, "stable");
+        let release = GiteaReleaseSource::new(&cfg)
+            .unwrap()
+            .get_by_tag("release/9.2")
+            .await
+            .unwrap();
+        assert_eq!(release.tag, "9.2");
+        assert_eq!(
+            *requests.lock().unwrap(),
+            ["/api/v1/repos/o/r/releases/tags/release%2F9.2"]
+        );
+    }
+}
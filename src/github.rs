@@ -1,62 +1,363 @@
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use clap::ValueEnum;
 use octocrab::Octocrab;
+use octocrab::service::middleware::retry::RetryConfig;
+use reqwest::StatusCode;
+use reqwest::header::{ETAG, HeaderMap, IF_NONE_MATCH};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::time::Duration;
 use url::Url;
 
+use crate::cache::Cache;
+use crate::changelog::{self, ComparedCommit, attribute_pull_requests, nightly_changelog};
 use crate::cli::CommandArgs;
+use crate::source::{PrismArtifact, PrismRelease, ReleaseSource, ReleaseType};
+use crate::system::newest_release;
 
-#[derive(Debug, Clone, ValueEnum)]
-pub enum ReleaseType {
-    Stable,
-    Nightly,
+/// How many times a single API call is attempted before giving up.
+const MAX_ATTEMPTS: u32 = 5;
+/// Upper bound for the exponential backoff between two attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Longest rate limit reset we are willing to sleep through.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(120);
+/// Page size used when walking every page of a listing.
+const PAGE_SIZE: usize = 100;
+/// Safety net against endpoints that never return a short page.
+const MAX_PAGES: usize = 50;
+/// Release payloads embed at most this many assets, the rest must be listed.
+const EMBEDDED_ASSETS_LIMIT: usize = 100;
+/// How many pages of workflow runs are searched for a usable nightly.
+const MAX_RUN_PAGES: u32 = 5;
+const RUNS_PER_PAGE: u32 = 20;
+/// Commits of a nightly changelog whose pull request is looked up, one request each.
+const MAX_PULL_REQUEST_LOOKUPS: usize = 100;
+
+#[derive(Deserialize)]
+struct Release {
+    id: u64,
+    name: Option<String>,
+    tag_name: String,
+    created_at: Option<DateTime<Utc>>,
+    body: Option<String>,
+    draft: bool,
+    prerelease: bool,
+    assets: Vec<Asset>,
 }
 
-#[derive(Debug)]
-pub struct PrismRelease {
-    pub name: String,
-    pub tag: String,
-    pub created_at: DateTime<Utc>,
-    pub assets: Vec<PrismArtifact>,
-    pub body: Option<String>,
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    size: usize,
+    url: Url,
+    browser_download_url: Url,
+    /// `sha256:<hex>`, missing on assets uploaded before GitHub started computing it
+    digest: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WorkflowRuns {
+    workflow_runs: Vec<WorkflowRun>,
+}
+
+#[derive(Deserialize)]
+struct WorkflowRun {
+    id: u64,
+    name: String,
+    head_sha: String,
+    conclusion: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl WorkflowRun {
+    fn succeeded(&self) -> bool {
+        self.conclusion.as_deref() == Some("success")
+    }
 }
 
+#[derive(Deserialize)]
+struct Artifacts {
+    artifacts: Vec<Artifact>,
+}
+
+#[derive(Deserialize)]
+struct Artifact {
+    id: u64,
+    name: String,
+    size_in_bytes: usize,
+    archive_download_url: Url,
+    digest: Option<String>,
+    #[serde(default)]
+    expired: bool,
+}
+
+#[derive(Deserialize)]
+struct Comparison {
+    html_url: Url,
+    commits: Vec<Commit>,
+}
+
+#[derive(Deserialize)]
+struct Commit {
+    sha: String,
+    html_url: Url,
+    commit: CommitDetails,
+    #[serde(default)]
+    parents: Vec<Parent>,
+}
+
+#[derive(Deserialize)]
+struct Parent {
+    sha: String,
+}
+
+#[derive(Deserialize)]
+struct CommitDetails {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct PullRequest {
+    number: u64,
+    title: String,
+    html_url: Url,
+    merged_at: Option<DateTime<Utc>>,
+}
+
+/// The GitHub rate limit was hit and did not reset within the retry budget.
 #[derive(Debug)]
-pub struct PrismArtifact {
-    // pub node_id: String,
-    pub name: String,
-    pub size_in_bytes: usize,
-    // pub url: Url,
-    pub download_url: Url,
-    // pub created_at: DateTime<Utc>,
-    // pub updated_at: DateTime<Utc>,
+pub struct RateLimitExhausted {
+    pub reset_at: Option<DateTime<Utc>>,
 }
 
-async fn get_latest_release(octocrab: &Octocrab, cfg: &CommandArgs) -> eyre::Result<PrismRelease> {
-    let release = octocrab
-        .repos(&cfg.repo_owner, &cfg.repo_name)
-        .releases()
-        .get_latest()
-        .await?;
-    let artifacts = release
-        .assets
-        .iter()
-        .map(|asset| PrismArtifact {
-            // node_id: asset.node_id.clone(),
-            name: asset.name.clone(),
-            size_in_bytes: asset.size.try_into().unwrap(),
-            // url: asset.url.clone(),
-            download_url: asset.browser_download_url.clone(),
-            // created_at: asset.created_at,
-            // updated_at: asset.updated_at,
+impl fmt::Display for RateLimitExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reset_at {
+            Some(reset_at) => write!(
+                f,
+                "GitHub API rate limit exhausted, it resets at {}",
+                reset_at.format("%+")
+            ),
+            None => write!(f, "GitHub API rate limit exhausted"),
+        }
+    }
+}
+
+impl std::error::Error for RateLimitExhausted {}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// The hex part of a `sha256:<hex>` digest, other algorithms are ignored.
+fn sha256_digest(digest: &str) -> Option<String> {
+    digest.strip_prefix("sha256:").map(str::to_lowercase)
+}
+
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.min(5)).min(MAX_BACKOFF)
+}
+
+/// GETs `route` and decodes the JSON answer, retrying on rate limits and server errors.
+///
+/// Answers are cached on disk and revalidated with their `ETag`, GitHub does not count
+/// `304 Not Modified` against the rate limit.
+async fn get_json<T: DeserializeOwned>(
+    octocrab: &Octocrab,
+    cfg: &CommandArgs,
+    route: &str,
+) -> eyre::Result<T> {
+    let cache = Cache::new(cfg);
+    let key = format!(
+        "{}{route}",
+        cfg.github_api_url.as_str().trim_end_matches('/')
+    );
+    let cached = cache.load(&key);
+    let mut headers = HeaderMap::new();
+    if let Some(entry) = &cached {
+        if cache.is_fresh(entry) {
+            log::debug!("Using cached {route}");
+            return Ok(serde_json::from_str(&entry.body)?);
+        }
+        if let Some(etag) = &entry.etag {
+            headers.insert(IF_NONE_MATCH, etag.parse()?);
+        }
+    }
+
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let response = match octocrab
+            ._get_with_headers(route, Some(headers.clone()))
+            .await
+        {
+            Ok(response) => response,
+            Err(err) if attempt < MAX_ATTEMPTS => {
+                let wait = backoff(attempt);
+                log::warn!("Request to {route} failed, retrying in {wait:?}: {err:?}");
+                tokio::time::sleep(wait).await;
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let status = response.status();
+        if let (StatusCode::NOT_MODIFIED, Some(entry)) = (status, &cached) {
+            log::debug!("{route} not modified");
+            cache.store(&key, entry.etag.clone(), &entry.body);
+            return Ok(serde_json::from_str(&entry.body)?);
+        }
+        if status.is_success() {
+            let etag = response
+                .headers()
+                .get(ETAG)
+                .and_then(|val| val.to_str().ok())
+                .map(String::from);
+            let body = octocrab.body_to_string(response).await?;
+            cache.store(&key, etag, &body);
+            return Ok(serde_json::from_str(&body)?);
+        }
+
+        let headers = response.headers();
+        let retry_after = header_u64(headers, "retry-after").map(Duration::from_secs);
+        let remaining = header_u64(headers, "x-ratelimit-remaining");
+        let reset_at = header_u64(headers, "x-ratelimit-reset")
+            .and_then(|reset| DateTime::<Utc>::from_timestamp(reset as i64, 0));
+        let rate_limited = status == StatusCode::TOO_MANY_REQUESTS
+            || (status == StatusCode::FORBIDDEN && (retry_after.is_some() || remaining == Some(0)));
+
+        if rate_limited {
+            let wait = retry_after
+                .or_else(|| reset_at.map(|reset| (reset - Utc::now()).to_std().unwrap_or_default()))
+                .unwrap_or_else(|| backoff(attempt));
+            if attempt >= MAX_ATTEMPTS || wait > MAX_RATE_LIMIT_WAIT {
+                let err = RateLimitExhausted { reset_at };
+                log::error!("{err} (attempt {attempt} on {route})");
+                return Err(err.into());
+            }
+            log::warn!("Rate limited on {route}, retrying in {wait:?}");
+            tokio::time::sleep(wait).await;
+            continue;
+        }
+        if status.is_server_error() && attempt < MAX_ATTEMPTS {
+            let wait = backoff(attempt);
+            log::warn!("{route} answered {status}, retrying in {wait:?}");
+            tokio::time::sleep(wait).await;
+            continue;
+        }
+        return Err(octocrab::map_github_error(response)
+            .await
+            .err()
+            .map(eyre::Report::from)
+            .unwrap_or_else(|| eyre::eyre!("{route} answered {status}")));
+    }
+}
+
+/// GETs every page of `route`, `PAGE_SIZE` items at a time, until a short page comes back.
+async fn get_all_pages<P: DeserializeOwned, T>(
+    octocrab: &Octocrab,
+    cfg: &CommandArgs,
+    route: &str,
+    items: impl Fn(P) -> Vec<T>,
+) -> eyre::Result<Vec<T>> {
+    let separator = if route.contains('?') { '&' } else { '?' };
+    let mut all = Vec::new();
+    for page in 1..=MAX_PAGES {
+        let batch = items(
+            get_json(
+                octocrab,
+                cfg,
+                &format!("{route}{separator}per_page={PAGE_SIZE}&page={page}"),
+            )
+            .await?,
+        );
+        let last_page = batch.len() < PAGE_SIZE;
+        all.extend(batch);
+        if last_page {
+            break;
+        }
+    }
+    Ok(all)
+}
+
+impl Release {
+    async fn into_prism(
+        mut self,
+        octocrab: &Octocrab,
+        cfg: &CommandArgs,
+    ) -> eyre::Result<PrismRelease> {
+        if self.assets.len() >= EMBEDDED_ASSETS_LIMIT {
+            self.assets = get_all_pages(
+                octocrab,
+                cfg,
+                &format!(
+                    "/repos/{}/{}/releases/{}/assets",
+                    cfg.repo_owner, cfg.repo_name, self.id
+                ),
+                |assets: Vec<Asset>| assets,
+            )
+            .await?;
+        }
+        let artifacts = self
+            .assets
+            .iter()
+            .map(|asset| PrismArtifact {
+                // node_id: asset.node_id.clone(),
+                name: asset.name.clone(),
+                size_in_bytes: asset.size,
+                api_url: Some(asset.url.clone()),
+                mirrors: Vec::new(),
+                sha256: asset.digest.as_deref().and_then(sha256_digest),
+                download_url: asset.browser_download_url.clone(),
+                // created_at: asset.created_at,
+                // updated_at: asset.updated_at,
+            })
+            .collect();
+        Ok(PrismRelease {
+            name: self.name.unwrap_or("".to_string()),
+            tag: self.tag_name.clone(),
+            created_at: self
+                .created_at
+                .ok_or_else(|| eyre::eyre!("Release {} has no creation date", self.tag_name))?,
+            assets: artifacts,
+            body: self.body,
+            prerelease: self.prerelease,
         })
-        .collect();
-    Ok(PrismRelease {
-        name: release.name.unwrap_or("".to_string()),
-        tag: release.tag_name.clone(),
-        created_at: release.created_at.unwrap(),
-        assets: artifacts,
-        body: release.body,
-    })
+    }
+}
+
+async fn get_latest_release(octocrab: &Octocrab, cfg: &CommandArgs) -> eyre::Result<PrismRelease> {
+    let release: Release = get_json(
+        octocrab,
+        cfg,
+        &format!(
+            "/repos/{}/{}/releases/latest",
+            cfg.repo_owner, cfg.repo_name
+        ),
+    )
+    .await?;
+    release.into_prism(octocrab, cfg).await
+}
+
+/// The newest release by version, prereleases included (the latest endpoint skips them).
+async fn get_newest_prerelease(
+    octocrab: &Octocrab,
+    cfg: &CommandArgs,
+) -> eyre::Result<PrismRelease> {
+    let releases: Vec<Release> = get_json(
+        octocrab,
+        cfg,
+        &format!(
+            "/repos/{}/{}/releases?per_page=100",
+            cfg.repo_owner, cfg.repo_name
+        ),
+    )
+    .await?;
+    let mut prism_releases = Vec::with_capacity(releases.len());
+    for release in releases.into_iter().filter(|release| !release.draft) {
+        prism_releases.push(release.into_prism(octocrab, cfg).await?);
+    }
+    newest_release(prism_releases).ok_or_else(|| eyre::eyre!("No release found"))
 }
 
 async fn get_commit_messages(
@@ -64,67 +365,169 @@ async fn get_commit_messages(
     cfg: &CommandArgs,
     base_sha: &str,
     head_sha: &str,
-) -> octocrab::Result<String> {
-    let comparison = octocrab
-        .commits(&cfg.repo_owner, &cfg.repo_name)
-        .compare(base_sha, head_sha)
-        .send()
-        .await?;
+) -> eyre::Result<String> {
+    let comparison: Comparison = get_json(
+        octocrab,
+        cfg,
+        &format!(
+            "/repos/{}/{}/compare/{base_sha}...{head_sha}",
+            cfg.repo_owner, cfg.repo_name
+        ),
+    )
+    .await?;
 
-    let changelog = comparison
+    let html_url = comparison.html_url;
+    let commits = comparison
         .commits
-        .iter()
-        .map(|commit| {
-            let sha = &commit.sha;
-            let message = &commit.commit.message;
-            let url = &commit.html_url;
-            format!("[{sha}] {message} ({url})")
+        .into_iter()
+        .map(|commit| ComparedCommit {
+            sha: commit.sha,
+            parents: commit
+                .parents
+                .into_iter()
+                .map(|parent| parent.sha)
+                .collect(),
+            message: commit.commit.message,
+            url: commit.html_url,
         })
-        .collect::<Vec<_>>()
-        .join("\n");
+        .collect();
+    // html_url is .../{owner}/{repo}/compare/{base}...{head}
+    let mut commits = attribute_pull_requests(commits, |number| {
+        html_url.join(&format!("../pull/{number}")).ok()
+    });
+    // the API also knows rebased pull requests and squashes whose subject was edited
+    for commit in commits.iter_mut().take(MAX_PULL_REQUEST_LOOKUPS) {
+        if let Some(pull_request) = get_merged_pull_request(octocrab, cfg, &commit.sha).await {
+            commit.pull_request = Some(pull_request);
+        }
+    }
 
-    let full_changelog_link = format!("\nFull changelog: {}", comparison.html_url);
+    Ok(nightly_changelog(&commits, html_url.as_str()))
+}
 
-    Ok(format!("#Changelog\n\n{changelog}\n{full_changelog_link}").to_owned())
+async fn get_merged_pull_request(
+    octocrab: &Octocrab,
+    cfg: &CommandArgs,
+    sha: &str,
+) -> Option<changelog::PullRequest> {
+    let route = format!(
+        "/repos/{}/{}/commits/{sha}/pulls",
+        cfg.repo_owner, cfg.repo_name
+    );
+    match get_json::<Vec<PullRequest>>(octocrab, cfg, &route).await {
+        Ok(pulls) => pulls
+            .into_iter()
+            .find(|pull| pull.merged_at.is_some())
+            .map(|pull| changelog::PullRequest {
+                reference: format!("#{}", pull.number),
+                title: pull.title,
+                url: pull.html_url,
+            }),
+        Err(err) => {
+            log::warn!("Failed to look up the pull request of {sha}: {:?}", err);
+            None
+        }
+    }
 }
 
-async fn get_latest_workflow_run(
+async fn list_workflow_runs(
     octocrab: &Octocrab,
     cfg: &CommandArgs,
-) -> eyre::Result<PrismRelease> {
-    let runs = octocrab
-        .workflows(&cfg.repo_owner, &cfg.repo_name)
-        .list_runs(&cfg.workflow_name)
-        .branch(&cfg.branch)
-        .per_page(1)
-        .send()
-        .await?;
-    let latest_run = runs.items.first().unwrap();
-    let page = octocrab
-        .actions()
-        .list_workflow_run_artifacts(&cfg.repo_owner, &cfg.repo_name, latest_run.id)
-        .send()
-        .await?;
-    let artifacts = page
-        .value
-        .unwrap()
-        .items
+    page: u32,
+    per_page: u32,
+) -> eyre::Result<Vec<WorkflowRun>> {
+    // in progress runs have nothing to download yet
+    let mut route = format!(
+        "/repos/{}/{}/actions/workflows/{}/runs?branch={}&status=completed&per_page={per_page}&page={page}",
+        cfg.repo_owner, cfg.repo_name, cfg.workflow_name, cfg.branch
+    );
+    if let Some(event) = &cfg.workflow_event {
+        route = format!("{route}&event={event}");
+    }
+    let runs: WorkflowRuns = get_json(octocrab, cfg, &route).await?;
+    Ok(runs.workflow_runs)
+}
+
+async fn get_run_artifacts(
+    octocrab: &Octocrab,
+    cfg: &CommandArgs,
+    run_id: u64,
+) -> eyre::Result<Vec<PrismArtifact>> {
+    let artifacts = get_all_pages(
+        octocrab,
+        cfg,
+        &format!(
+            "/repos/{}/{}/actions/runs/{}/artifacts",
+            cfg.repo_owner, cfg.repo_name, run_id
+        ),
+        |page: Artifacts| page.artifacts,
+    )
+    .await?;
+    artifacts
         .iter()
-        .map(|asset| PrismArtifact {
-            // node_id: asset.node_id.clone(),
-            name: asset.name.clone(),
-            size_in_bytes: asset.size_in_bytes,
-            // url: asset.url.clone(),
-            download_url: format!(
-                "https://nightly.link/{}/{}/actions/artifacts/{}.zip",
-                cfg.repo_owner, cfg.repo_name, asset.id
-            )
-            .parse()
-            .unwrap(),
-            // created_at: asset.created_at,
-            // updated_at: asset.updated_at,
+        .filter(|asset| !asset.expired)
+        .map(|asset| {
+            Ok(PrismArtifact {
+                // node_id: asset.node_id.clone(),
+                name: asset.name.clone(),
+                size_in_bytes: asset.size_in_bytes,
+                // used instead of the proxy when a token is available
+                api_url: Some(asset.archive_download_url.clone()),
+                mirrors: Vec::new(),
+                sha256: asset.digest.as_deref().and_then(sha256_digest),
+                download_url: cfg
+                    .artifact_url_template
+                    .replace("{host}", cfg.artifact_host.as_str().trim_end_matches('/'))
+                    .replace("{owner}", &cfg.repo_owner)
+                    .replace("{repo}", &cfg.repo_name)
+                    .replace("{id}", &asset.id.to_string())
+                    .parse()?,
+                // created_at: asset.created_at,
+                // updated_at: asset.updated_at,
+            })
         })
-        .collect();
+        .collect()
+}
+
+/// The newest successful run that still has artifacts, falling back through older runs.
+async fn find_latest_usable_run(
+    octocrab: &Octocrab,
+    cfg: &CommandArgs,
+) -> eyre::Result<(WorkflowRun, Vec<PrismArtifact>)> {
+    for page in 1..=MAX_RUN_PAGES {
+        let runs = list_workflow_runs(octocrab, cfg, page, RUNS_PER_PAGE).await?;
+        if runs.is_empty() {
+            break;
+        }
+        for run in runs {
+            if !run.succeeded() {
+                log::info!(
+                    "Skipping run {} because it concluded with {:?}",
+                    run.id,
+                    run.conclusion
+                );
+                continue;
+            }
+            let artifacts = get_run_artifacts(octocrab, cfg, run.id).await?;
+            if artifacts.is_empty() {
+                log::info!("Skipping run {} because it has no artifacts", run.id);
+                continue;
+            }
+            return Ok((run, artifacts));
+        }
+    }
+    Err(eyre::eyre!(
+        "No successful run of {} with artifacts on {}",
+        cfg.workflow_name,
+        cfg.branch
+    ))
+}
+
+async fn get_latest_workflow_run(
+    octocrab: &Octocrab,
+    cfg: &CommandArgs,
+) -> eyre::Result<PrismRelease> {
+    let (latest_run, artifacts) = find_latest_usable_run(octocrab, cfg).await?;
 
     let changelog = get_commit_messages(
         octocrab,
@@ -139,13 +542,122 @@ async fn get_latest_workflow_run(
         created_at: latest_run.created_at,
         assets: artifacts,
         body: Some(changelog),
+        prerelease: false,
     })
 }
 
-pub async fn get_latest(cfg: &CommandArgs) -> eyre::Result<PrismRelease> {
-    let octocrab = Octocrab::builder().build()?;
-    match cfg.release_type {
-        ReleaseType::Stable => get_latest_release(&octocrab, cfg).await,
-        ReleaseType::Nightly => get_latest_workflow_run(&octocrab, cfg).await,
+/// `value` escaped so that it stays a single segment of a route, even with `/`, `?` or `#`.
+fn path_segment(value: &str) -> String {
+    let mut url = Url::parse("https://localhost/").expect("a valid url");
+    url.path_segments_mut()
+        .expect("a base url")
+        .pop_if_empty()
+        .push(value);
+    url.path()[1..].to_owned()
+}
+
+fn build_octocrab(cfg: &CommandArgs) -> eyre::Result<Octocrab> {
+    let mut builder = Octocrab::builder()
+        .base_uri(cfg.github_api_url.as_str())?
+        // octocrab would re-send rate limited requests right away, get_json backs off instead
+        .add_retry_config(RetryConfig::None);
+    if let Some(token) = cfg.github_token()? {
+        builder = builder.personal_token(token);
+    }
+    Ok(builder.build()?)
+}
+
+/// Stable (and beta) releases, as published on the GitHub releases page.
+pub struct GithubReleaseSource<'a> {
+    octocrab: Octocrab,
+    cfg: &'a CommandArgs,
+}
+
+impl<'a> GithubReleaseSource<'a> {
+    pub fn new(cfg: &'a CommandArgs) -> eyre::Result<Self> {
+        Ok(Self {
+            octocrab: build_octocrab(cfg)?,
+            cfg,
+        })
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for GithubReleaseSource<'_> {
+    async fn get_latest(&self) -> eyre::Result<PrismRelease> {
+        match self.cfg.release_type {
+            ReleaseType::Beta => get_newest_prerelease(&self.octocrab, self.cfg).await,
+            _ => get_latest_release(&self.octocrab, self.cfg).await,
+        }
+    }
+
+    async fn get_by_tag(&self, tag: &str) -> eyre::Result<PrismRelease> {
+        let release: Release = get_json(
+            &self.octocrab,
+            self.cfg,
+            &format!(
+                "/repos/{}/{}/releases/tags/{}",
+                self.cfg.repo_owner,
+                self.cfg.repo_name,
+                path_segment(tag)
+            ),
+        )
+        .await?;
+        release.into_prism(&self.octocrab, self.cfg).await
+    }
+
+    async fn list(&self, page: u32, per_page: u32) -> eyre::Result<Vec<PrismRelease>> {
+        let releases: Vec<Release> = get_json(
+            &self.octocrab,
+            self.cfg,
+            &format!(
+                "/repos/{}/{}/releases?per_page={per_page}&page={page}",
+                self.cfg.repo_owner, self.cfg.repo_name
+            ),
+        )
+        .await?;
+        let mut prism_releases = Vec::with_capacity(releases.len());
+        for release in releases.into_iter().filter(|release| !release.draft) {
+            prism_releases.push(release.into_prism(&self.octocrab, self.cfg).await?);
+        }
+        Ok(prism_releases)
+    }
+}
+
+/// Nightly builds, taken from the GitHub Actions workflow runs.
+pub struct GithubWorkflowSource<'a> {
+    octocrab: Octocrab,
+    cfg: &'a CommandArgs,
+}
+
+impl<'a> GithubWorkflowSource<'a> {
+    pub fn new(cfg: &'a CommandArgs) -> eyre::Result<Self> {
+        Ok(Self {
+            octocrab: build_octocrab(cfg)?,
+            cfg,
+        })
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for GithubWorkflowSource<'_> {
+    async fn get_latest(&self) -> eyre::Result<PrismRelease> {
+        get_latest_workflow_run(&self.octocrab, self.cfg).await
+    }
+
+    async fn list(&self, page: u32, per_page: u32) -> eyre::Result<Vec<PrismRelease>> {
+        let runs = list_workflow_runs(&self.octocrab, self.cfg, page, per_page).await?;
+        let mut releases = Vec::with_capacity(runs.len());
+        for run in runs.into_iter().filter(WorkflowRun::succeeded) {
+            releases.push(PrismRelease {
+                assets: get_run_artifacts(&self.octocrab, self.cfg, run.id).await?,
+                name: run.name,
+                tag: run.head_sha,
+                created_at: run.created_at,
+                body: None,
+                prerelease: false,
+            });
+        }
+        Ok(releases)
     }
 }
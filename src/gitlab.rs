@@ -0,0 +1,414 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use url::{Url, form_urlencoded};
+
+use crate::changelog::{self, ComparedCommit, attribute_pull_requests, nightly_changelog};
+use crate::cli::CommandArgs;
+use crate::rest::{as_base, get_json};
+use crate::source::{PrismArtifact, PrismRelease, ReleaseSource, ReleaseType};
+use crate::system::{is_prerelease_tag, newest_release};
+
+/// Instance used when `--source-url` is not given.
+const DEFAULT_INSTANCE: &str = "https://gitlab.com";
+/// Commits of a nightly changelog whose merge request is looked up, one request each.
+const MAX_MERGE_REQUEST_LOOKUPS: usize = 100;
+
+#[derive(Deserialize)]
+struct Release {
+    name: Option<String>,
+    tag_name: String,
+    created_at: DateTime<Utc>,
+    description: Option<String>,
+    assets: ReleaseAssets,
+}
+
+impl Release {
+    /// Enough of the release to compare versions, without its package files.
+    fn without_assets(&self) -> PrismRelease {
+        PrismRelease {
+            name: self.name.clone().unwrap_or_default(),
+            tag: self.tag_name.clone(),
+            created_at: self.created_at,
+            assets: Vec::new(),
+            body: None,
+            prerelease: is_prerelease_tag(&self.tag_name),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ReleaseAssets {
+    links: Vec<AssetLink>,
+}
+
+#[derive(Deserialize)]
+struct AssetLink {
+    name: String,
+    url: Url,
+    direct_asset_url: Option<Url>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    id: u64,
+    name: String,
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct PackageFile {
+    file_name: String,
+    size: usize,
+    file_sha256: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Pipeline {
+    id: u64,
+    sha: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct Job {
+    id: u64,
+    name: String,
+    artifacts_file: Option<ArtifactsFile>,
+}
+
+#[derive(Deserialize)]
+struct ArtifactsFile {
+    size: usize,
+}
+
+#[derive(Deserialize)]
+struct Comparison {
+    commits: Vec<Commit>,
+}
+
+#[derive(Deserialize)]
+struct Commit {
+    id: String,
+    message: String,
+    web_url: Url,
+    #[serde(default)]
+    parent_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct MergeRequest {
+    iid: u64,
+    title: String,
+    web_url: Url,
+    state: String,
+}
+
+/// Thin wrapper over the GitLab v4 REST API of a single project.
+struct GitlabProject<'a> {
+    client: Client,
+    instance: Url,
+    project_url: Url,
+    cfg: &'a CommandArgs,
+}
+
+impl<'a> GitlabProject<'a> {
+    fn new(cfg: &'a CommandArgs) -> eyre::Result<Self> {
+        let instance = as_base(&match &cfg.source_url {
+            Some(url) => url.clone(),
+            None => DEFAULT_INSTANCE.parse()?,
+        });
+        // the project id can be replaced by its url-encoded path
+        let project = format!("{}/{}", cfg.repo_owner, cfg.repo_name).replace('/', "%2F");
+        Ok(Self {
+            client: Client::new(),
+            project_url: instance.join(&format!("api/v4/projects/{project}/"))?,
+            instance,
+            cfg,
+        })
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> eyre::Result<T> {
+        get_json(&self.client, self.cfg, self.project_url.join(path)?).await
+    }
+
+    /// Files uploaded to the generic package registry under the release version.
+    async fn get_package_files(&self, version: &str) -> eyre::Result<Vec<PrismArtifact>> {
+        let version: String = form_urlencoded::byte_serialize(version.as_bytes()).collect();
+        let packages: Vec<Package> = self
+            .get(&format!(
+                "packages?package_type=generic&package_version={version}"
+            ))
+            .await?;
+        let mut artifacts = Vec::new();
+        for package in packages {
+            let files: Vec<PackageFile> = self
+                .get(&format!(
+                    "packages/{}/package_files?per_page=100",
+                    package.id
+                ))
+                .await?;
+            for file in files {
+                artifacts.push(PrismArtifact {
+                    download_url: self.project_url.join(&format!(
+                        "packages/generic/{}/{}/{}",
+                        package.name, package.version, file.file_name
+                    ))?,
+                    name: file.file_name,
+                    size_in_bytes: file.size,
+                    api_url: None,
+                    mirrors: Vec::new(),
+                    sha256: file.file_sha256,
+                });
+            }
+        }
+        Ok(artifacts)
+    }
+
+    async fn get_commit_messages(&self, base_sha: &str, head_sha: &str) -> eyre::Result<String> {
+        let comparison: Comparison = self
+            .get(&format!("repository/compare?from={base_sha}&to={head_sha}"))
+            .await?;
+
+        let compare_url = format!(
+            "{}{}/{}/-/compare/{base_sha}...{head_sha}",
+            self.instance, self.cfg.repo_owner, self.cfg.repo_name
+        );
+        let commits = comparison
+            .commits
+            .into_iter()
+            .map(|commit| ComparedCommit {
+                sha: commit.id,
+                parents: commit.parent_ids,
+                message: commit.message,
+                url: commit.web_url,
+            })
+            .collect();
+        let mut commits = attribute_pull_requests(commits, |number| {
+            self.instance
+                .join(&format!(
+                    "{}/{}/-/merge_requests/{number}",
+                    self.cfg.repo_owner, self.cfg.repo_name
+                ))
+                .ok()
+        });
+        // the API also knows rebased merge requests and squashes whose subject was edited
+        for commit in commits.iter_mut().take(MAX_MERGE_REQUEST_LOOKUPS) {
+            if let Some(merge_request) = self.get_merge_request(&commit.sha).await {
+                commit.pull_request = Some(merge_request);
+            }
+        }
+        Ok(nightly_changelog(&commits, &compare_url))
+    }
+
+    async fn get_merge_request(&self, sha: &str) -> Option<changelog::PullRequest> {
+        let path = format!("repository/commits/{sha}/merge_requests");
+        match self.get::<Vec<MergeRequest>>(&path).await {
+            Ok(requests) => requests
+                .into_iter()
+                .find(|request| request.state == "merged")
+                .map(|request| changelog::PullRequest {
+                    reference: format!("!{}", request.iid),
+                    title: request.title,
+                    url: request.web_url,
+                }),
+            Err(err) => {
+                log::warn!("Failed to look up the merge request of {sha}: {:?}", err);
+                None
+            }
+        }
+    }
+}
+
+/// Stable (and beta) releases, with their asset links and generic package files.
+pub struct GitlabReleaseSource<'a> {
+    project: GitlabProject<'a>,
+}
+
+impl<'a> GitlabReleaseSource<'a> {
+    pub fn new(cfg: &'a CommandArgs) -> eyre::Result<Self> {
+        Ok(Self {
+            project: GitlabProject::new(cfg)?,
+        })
+    }
+}
+
+impl GitlabReleaseSource<'_> {
+    async fn to_prism(&self, release: Release) -> eyre::Result<PrismRelease> {
+        // projects with the package registry disabled still have their asset links
+        let mut artifacts = match self.project.get_package_files(&release.tag_name).await {
+            Ok(v) => v,
+            Err(err) => {
+                log::warn!(
+                    "Failed to list the package files of {}: {:?}",
+                    release.tag_name,
+                    err
+                );
+                Vec::new()
+            }
+        };
+        for link in release.assets.links {
+            // links usually point at the package files we already know the size of
+            if artifacts.iter().any(|artifact| artifact.name == link.name) {
+                continue;
+            }
+            artifacts.push(PrismArtifact {
+                name: link.name,
+                // GitLab does not know the size of linked files
+                size_in_bytes: 0,
+                api_url: None,
+                mirrors: Vec::new(),
+                sha256: None,
+                download_url: link.direct_asset_url.unwrap_or(link.url),
+            });
+        }
+        Ok(PrismRelease {
+            name: release.name.unwrap_or_default(),
+            // GitLab has no prerelease flag, the tag is all we have
+            prerelease: is_prerelease_tag(&release.tag_name),
+            tag: release.tag_name,
+            created_at: release.created_at,
+            assets: artifacts,
+            body: release.description,
+        })
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for GitlabReleaseSource<'_> {
+    async fn get_latest(&self) -> eyre::Result<PrismRelease> {
+        // the latest permalink would hand release candidates to stable users too
+        let include_prereleases = self.project.cfg.release_type == ReleaseType::Beta;
+        let releases: Vec<Release> = self.project.get("releases?per_page=100").await?;
+        let candidates = releases
+            .iter()
+            .filter(|release| include_prereleases || !is_prerelease_tag(&release.tag_name))
+            .map(Release::without_assets)
+            .collect();
+        let newest = newest_release(candidates).ok_or_else(|| eyre::eyre!("No release found"))?;
+        // only the chosen release needs its package files looked up
+        let release = releases
+            .into_iter()
+            .find(|release| release.tag_name == newest.tag)
+            .ok_or_else(|| eyre::eyre!("No release found"))?;
+        self.to_prism(release).await
+    }
+
+    async fn get_by_tag(&self, tag: &str) -> eyre::Result<PrismRelease> {
+        let mut url = self.project.project_url.join("releases/")?;
+        // tags may contain `/`, `?` or `#`, they must stay a single path segment
+        url.path_segments_mut()
+            .map_err(|_| eyre::eyre!("The GitLab API url can not be a base"))?
+            .pop_if_empty()
+            .push(tag);
+        let release: Release = get_json(&self.project.client, self.project.cfg, url).await?;
+        self.to_prism(release).await
+    }
+
+    async fn list(&self, page: u32, per_page: u32) -> eyre::Result<Vec<PrismRelease>> {
+        let releases: Vec<Release> = self
+            .project
+            .get(&format!("releases?per_page={per_page}&page={page}"))
+            .await?;
+        let mut result = Vec::with_capacity(releases.len());
+        for release in releases {
+            result.push(self.to_prism(release).await?);
+        }
+        Ok(result)
+    }
+}
+
+/// Nightly builds, taken from the job artifacts of the latest successful pipeline.
+pub struct GitlabPipelineSource<'a> {
+    project: GitlabProject<'a>,
+}
+
+impl<'a> GitlabPipelineSource<'a> {
+    pub fn new(cfg: &'a CommandArgs) -> eyre::Result<Self> {
+        Ok(Self {
+            project: GitlabProject::new(cfg)?,
+        })
+    }
+}
+
+impl GitlabPipelineSource<'_> {
+    async fn list_pipelines(&self, page: u32, per_page: u32) -> eyre::Result<Vec<Pipeline>> {
+        self.project
+            .get(&format!(
+                "pipelines?ref={}&status=success&per_page={per_page}&page={page}",
+                self.project.cfg.branch
+            ))
+            .await
+    }
+
+    async fn get_job_artifacts(&self, pipeline_id: u64) -> eyre::Result<Vec<PrismArtifact>> {
+        let jobs: Vec<Job> = self
+            .project
+            .get(&format!("pipelines/{pipeline_id}/jobs?per_page=100"))
+            .await?;
+        let mut artifacts = Vec::new();
+        for job in jobs {
+            let Some(file) = job.artifacts_file else {
+                continue;
+            };
+            artifacts.push(PrismArtifact {
+                download_url: self
+                    .project
+                    .project_url
+                    .join(&format!("jobs/{}/artifacts", job.id))?,
+                name: job.name,
+                size_in_bytes: file.size,
+                api_url: None,
+                mirrors: Vec::new(),
+                sha256: None,
+            });
+        }
+        Ok(artifacts)
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for GitlabPipelineSource<'_> {
+    async fn get_latest(&self) -> eyre::Result<PrismRelease> {
+        let cfg = self.project.cfg;
+        let pipelines = self.list_pipelines(1, 1).await?;
+        let pipeline = pipelines
+            .first()
+            .ok_or_else(|| eyre::eyre!("No successful pipeline on {}", cfg.branch))?;
+        let artifacts = self.get_job_artifacts(pipeline.id).await?;
+
+        let changelog = match cfg.git_commit.as_deref() {
+            Some(commit) => Some(
+                self.project
+                    .get_commit_messages(commit, &pipeline.sha)
+                    .await?,
+            ),
+            None => None,
+        };
+        Ok(PrismRelease {
+            name: format!("Pipeline #{}", pipeline.id),
+            tag: pipeline.sha.clone(),
+            created_at: pipeline.created_at,
+            assets: artifacts,
+            body: changelog,
+            prerelease: false,
+        })
+    }
+
+    async fn list(&self, page: u32, per_page: u32) -> eyre::Result<Vec<PrismRelease>> {
+        let mut releases = Vec::new();
+        for pipeline in self.list_pipelines(page, per_page).await? {
+            releases.push(PrismRelease {
+                name: format!("Pipeline #{}", pipeline.id),
+                assets: self.get_job_artifacts(pipeline.id).await?,
+                tag: pipeline.sha,
+                created_at: pipeline.created_at,
+                body: None,
+                prerelease: false,
+            });
+        }
+        Ok(releases)
+    }
+}
@@ -0,0 +1,72 @@
+use serde_json::json;
+
+use crate::source::{PrismRelease, ReleaseSource};
+use crate::system::{InstallationType, select_valid_artifacts};
+
+fn matching_assets(
+    release: &PrismRelease,
+    build_artifact: &str,
+    installation_type: &InstallationType,
+) -> Vec<String> {
+    // no match is not an error here, the entry is still worth showing
+    select_valid_artifacts(
+        release,
+        build_artifact.to_owned(),
+        installation_type.clone(),
+    )
+    .map(|artifacts| artifacts.iter().map(|a| a.name.clone()).collect())
+    .unwrap_or_default()
+}
+
+pub async fn list_releases(
+    source: &dyn ReleaseSource,
+    page: u32,
+    per_page: u32,
+    json: bool,
+    build_artifact: &str,
+    installation_type: &InstallationType,
+) -> eyre::Result<()> {
+    let releases = source.list(page.max(1), per_page).await?;
+
+    if json {
+        let entries: Vec<_> = releases
+            .iter()
+            .map(|release| {
+                json!({
+                    "tag": release.tag,
+                    "name": release.name,
+                    "date": release.created_at,
+                    "prerelease": release.prerelease,
+                    "assets": release.assets.iter().map(|asset| json!({
+                        "name": asset.name,
+                        "size": asset.size_in_bytes,
+                        "url": asset.download_url,
+                    })).collect::<Vec<_>>(),
+                    "matching_assets": matching_assets(release, build_artifact, installation_type),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    println!(
+        "{:<42} {:<20} {:<10} MATCHING ASSETS",
+        "TAG", "DATE", "PRERELEASE"
+    );
+    for release in &releases {
+        let matching = matching_assets(release, build_artifact, installation_type);
+        println!(
+            "{:<42} {:<20} {:<10} {}",
+            release.tag,
+            release.created_at.format("%Y-%m-%d %H:%M"),
+            if release.prerelease { "yes" } else { "no" },
+            if matching.is_empty() {
+                "-".to_string()
+            } else {
+                matching.join(", ")
+            }
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Releases copied to a local directory, for machines without network access.
+//!
+//! ```text
+//! releases/
+//!   9.2/
+//!     release.toml
+//!     PrismLauncher-Linux-Qt6-Portable-9.2.tar.gz
+//! ```
+//!
+//! `release.toml` is optional and takes the keys of a feed release: `name`, `channel`,
+//! `date` (an RFC 3339 string, the directory modification time otherwise) and `notes`.
+//! Every other file of the directory is an asset.
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use crate::cli::CommandArgs;
+use crate::source::{PrismArtifact, PrismRelease, ReleaseSource, ReleaseType, paginate};
+use crate::system::newest_release;
+
+const METADATA_FILE: &str = "release.toml";
+
+#[derive(Deserialize, Default)]
+struct Metadata {
+    name: Option<String>,
+    channel: Option<ReleaseType>,
+    date: Option<DateTime<Utc>>,
+    notes: Option<String>,
+}
+
+struct LocalRelease {
+    channel: ReleaseType,
+    release: PrismRelease,
+}
+
+fn read_release(dir: &Path) -> eyre::Result<LocalRelease> {
+    let tag = dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| eyre::eyre!("Release directory {:?} is not valid UTF-8", dir))?
+        .to_owned();
+    let metadata_path = dir.join(METADATA_FILE);
+    let metadata: Metadata = if metadata_path.is_file() {
+        toml::from_str(&fs::read_to_string(&metadata_path)?)?
+    } else {
+        Metadata::default()
+    };
+
+    let mut assets = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == METADATA_FILE || !entry.file_type()?.is_file() {
+            continue;
+        }
+        assets.push(PrismArtifact {
+            download_url: Url::from_file_path(entry.path())
+                .map_err(|_| eyre::eyre!("Invalid asset path {:?}", entry.path()))?,
+            name,
+            size_in_bytes: entry.metadata()?.len() as usize,
+            api_url: None,
+            mirrors: Vec::new(),
+            sha256: None,
+        });
+    }
+    assets.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let created_at = match metadata.date {
+        Some(date) => date,
+        None => fs::metadata(dir)?.modified()?.into(),
+    };
+    let channel = metadata.channel.unwrap_or(ReleaseType::Stable);
+    Ok(LocalRelease {
+        release: PrismRelease {
+            name: metadata.name.unwrap_or_else(|| tag.clone()),
+            tag,
+            created_at,
+            assets,
+            body: metadata.notes,
+            prerelease: channel == ReleaseType::Beta,
+        },
+        channel,
+    })
+}
+
+/// Releases laid out as `<tag>/<assets>` in a local directory.
+pub struct LocalSource<'a> {
+    dir: PathBuf,
+    cfg: &'a CommandArgs,
+}
+
+impl<'a> LocalSource<'a> {
+    pub fn new(cfg: &'a CommandArgs) -> eyre::Result<Self> {
+        let dir = match (&cfg.source_path, &cfg.source_url) {
+            (Some(path), _) => path.clone(),
+            (None, Some(url)) if url.scheme() == "file" => url
+                .to_file_path()
+                .map_err(|_| eyre::eyre!("Invalid directory url {url}"))?,
+            _ => {
+                return Err(eyre::eyre!(
+                    "The local source needs a directory in --source-path or a file:// --source-url"
+                ));
+            }
+        };
+        Ok(Self { dir, cfg })
+    }
+
+    /// Releases of the configured channel, in directory order.
+    fn get_releases(&self) -> eyre::Result<Vec<PrismRelease>> {
+        let mut releases = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let local = read_release(&entry.path())?;
+            if self.cfg.release_type.includes(&local.channel) {
+                releases.push(local.release);
+            }
+        }
+        Ok(releases)
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for LocalSource<'_> {
+    async fn get_latest(&self) -> eyre::Result<PrismRelease> {
+        let releases = self.get_releases()?;
+        let release_type = &self.cfg.release_type;
+        let release = if *release_type == ReleaseType::Beta {
+            newest_release(releases)
+        } else {
+            releases
+                .into_iter()
+                .max_by_key(|release| release.created_at)
+        };
+        release.ok_or_else(|| eyre::eyre!("No {release_type:?} release in {:?}", self.dir))
+    }
+
+    async fn get_by_tag(&self, tag: &str) -> eyre::Result<PrismRelease> {
+        let dir = self.dir.join(tag);
+        if !dir.is_dir() {
+            return Err(eyre::eyre!("No release {tag:?} in {:?}", self.dir));
+        }
+        Ok(read_release(&dir)?.release)
+    }
+
+    async fn list(&self, page: u32, per_page: u32) -> eyre::Result<Vec<PrismRelease>> {
+        let mut releases = self.get_releases()?;
+        releases.sort_by_key(|release| Reverse(release.created_at));
+        Ok(paginate(releases, page, per_page).collect())
+    }
+}
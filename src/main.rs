@@ -3,21 +3,42 @@ use clap::Parser;
 use file_lock::FileLock;
 use install::{call_appimage_update, run_installer};
 use std::fs::{self, create_dir};
+use std::process::ExitCode;
 use tempfile::tempdir;
 use tokio::{self, process::Command};
 
-use download::fetch_url;
+use changelog::skipped_release_notes;
+use check::print_check;
+use checksum::expected_sha256;
+use download::fetch_artifact;
+use exit::Status;
+use list::list_releases;
+use signature::{trusted_keys, verify_signature};
 use system::{
     InstallationType, compare_tags, get_exe_root_dir, get_instalation_type, select_valid_artifacts,
 };
-use unpack::unarchive_loop;
+use unpack::{UnsafeArchive, unarchive_loop};
 
+mod appcast;
 mod backup;
+mod cache;
+mod changelog;
+mod check;
+mod checksum;
 mod cli;
 mod download;
+mod exit;
+mod feed;
 mod file_lock;
+mod gitea;
 mod github;
+mod gitlab;
 mod install;
+mod list;
+mod local;
+mod rest;
+mod signature;
+mod source;
 mod system;
 mod unpack;
 
@@ -39,7 +60,13 @@ fn init_log(args: &cli::CommandArgs) -> eyre::Result<()> {
         // Output to stdout, files, and other Dispatch configurations
         .chain(fern::log_file(&args.log_path)?);
     if args.log_stdout {
-        log_cfg = log_cfg.chain(std::io::stdout());
+        // stdout is reserved for the documents of `check --json` and `list --json`
+        log_cfg = match args.command {
+            cli::Commands::Check { json: true } | cli::Commands::List { json: true, .. } => {
+                log_cfg.chain(std::io::stderr())
+            }
+            _ => log_cfg.chain(std::io::stdout()),
+        };
     }
     // Apply globally
     log_cfg.apply()?;
@@ -47,8 +74,19 @@ fn init_log(args: &cli::CommandArgs) -> eyre::Result<()> {
 }
 
 #[tokio::main]
-async fn main() -> Result<(), eyre::Report> {
-    let cli = cli::CommandArgs::parse();
+async fn main() -> ExitCode {
+    let status = match run().await {
+        Ok(status) => status,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            Status::of_error(&err)
+        }
+    };
+    status.into()
+}
+
+async fn run() -> eyre::Result<Status> {
+    let mut cli = cli::CommandArgs::parse();
     init_log(&cli)?;
 
     let build_artifact = match cli.build_artifact.as_deref() {
@@ -59,39 +97,109 @@ async fn main() -> Result<(), eyre::Report> {
         Some(v) => v,
     };
 
-    let release = match github::get_latest(&cli).await {
-        Ok(release) => release,
+    let root_dir = if cli.root_path.exists() {
+        cli.root_path.clone()
+    } else {
+        // fallback just in case
+        match get_exe_root_dir() {
+            Some(dir) => dir,
+            None => {
+                log::error!("Failed to retrieve root directory");
+                return Err(eyre::eyre!("Missing root directory"));
+            }
+        }
+    };
+    // a --root-path that does not exist must not be created by the cache
+    cli.cache_dir.get_or_insert_with(|| root_dir.join("cache"));
+    let installation_type = get_instalation_type(&root_dir);
+    let source = match source::get_source(&cli) {
+        Ok(source) => source,
         Err(err) => {
-            log::error!("Failed to get latest release: {:?}", err);
+            log::error!("Failed to create release source: {:?}", err);
             return Err(err);
         }
     };
+    if let cli::Commands::List {
+        page,
+        per_page,
+        json,
+    } = cli.command
+    {
+        if let Err(err) = list_releases(
+            source.as_ref(),
+            page,
+            per_page,
+            json,
+            build_artifact,
+            &installation_type,
+        )
+        .await
+        {
+            log::error!("Failed to list releases: {:?}", err);
+            return Err(err.wrap_err(Status::SourceUnavailable));
+        }
+        return Ok(Status::Success);
+    }
 
-    match cli.release_type {
-        github::ReleaseType::Stable => match cli.prism_version.as_deref() {
-            Some("") | None => {
-                log::error!("Error: prism_version is missing or empty.");
-                return Err(eyre::eyre!("prism_version is missing or empty"));
+    let release = match &cli.target_version {
+        Some(tag) => source.get_by_tag(tag).await,
+        None => source.get_latest().await,
+    };
+    let mut release = match release {
+        Ok(release) => release,
+        Err(err) => {
+            log::error!("Failed to get release: {:?}", err);
+            return Err(err.wrap_err(Status::SourceUnavailable));
+        }
+    };
+    if release.prerelease {
+        log::info!("{:?} is a prerelease", release.tag);
+    }
+    if let Err(err) = release.apply_mirrors(&cli.mirrors) {
+        log::error!("Invalid mirror template: {:?}", err);
+        return Err(err);
+    }
+
+    let update_available = match (&cli.target_version, &cli.release_type) {
+        // pinned versions are installed even when older, this is how we roll back
+        (Some(_), _) => {
+            if cli.prism_version.as_deref() == Some(release.tag.as_str()) {
+                log::info!(
+                    "Nothing to do current version is already the pinned one: {:?}",
+                    release.tag
+                );
+                false
+            } else {
+                log::info!("Installing pinned version {:?}", release.tag);
+                true
             }
-            Some(actual_version) => {
-                match compare_tags(release.tag.clone(), actual_version.to_owned()) {
-                    Ok(false) => {
-                        log::info!(
-                            "Nothing to do current version is greater or equal to the latest release: {:?} vs {:?}",
-                            release.tag,
-                            actual_version
-                        );
-                        return Ok(());
-                    }
-                    Ok(true) => {}
-                    Err(err) => {
-                        log::error!("Failed to compare versions: {:?}", err);
-                        return Err(err);
+        }
+        (None, source::ReleaseType::Stable | source::ReleaseType::Beta) => {
+            match cli.prism_version.as_deref() {
+                Some("") | None => {
+                    log::error!("Error: prism_version is missing or empty.");
+                    return Err(eyre::eyre!("prism_version is missing or empty"));
+                }
+                Some(actual_version) => {
+                    match compare_tags(release.tag.clone(), actual_version.to_owned()) {
+                        Ok(false) => {
+                            log::info!(
+                                "Nothing to do current version is greater or equal to the latest release: {:?} vs {:?}",
+                                release.tag,
+                                actual_version
+                            );
+                            false
+                        }
+                        Ok(true) => true,
+                        Err(err) => {
+                            log::error!("Failed to compare versions: {:?}", err);
+                            return Err(err);
+                        }
                     }
                 }
             }
-        },
-        github::ReleaseType::Nightly => match cli.git_commit.as_deref() {
+        }
+        (None, source::ReleaseType::Nightly) => match cli.git_commit.as_deref() {
             Some("") | None => {
                 log::error!("Error: git_commit is missing or empty.");
                 return Err(eyre::eyre!("git_commit is missing or empty"));
@@ -103,24 +211,20 @@ async fn main() -> Result<(), eyre::Report> {
                         release.tag,
                         commit
                     );
-                    return Ok(());
                 }
+                commit != release.tag
             }
         },
     };
-    let root_dir = if cli.root_path.exists() {
-        cli.root_path
-    } else {
-        // fallback just in case
-        match get_exe_root_dir() {
-            Some(dir) => dir,
-            None => {
-                log::error!("Failed to retrieve root directory");
-                return Err(eyre::eyre!("Missing root directory"));
-            }
+    if !update_available {
+        if let cli::Commands::Check { json: true } = cli.command
+            && let Err(err) = print_check(&cli, &release, &[], None, false, true)
+        {
+            log::error!("Failed to print check result: {:?}", err);
+            return Err(err);
         }
-    };
-    let installation_type = get_instalation_type(&root_dir);
+        return Ok(Status::Success);
+    }
     let valid_artifacts = match select_valid_artifacts(
         &release,
         build_artifact.to_owned(),
@@ -129,25 +233,47 @@ async fn main() -> Result<(), eyre::Report> {
         Ok(valid_artifacts) => valid_artifacts,
         Err(err) => {
             log::error!("Failed to filter artifacts: {:?}", err);
-            return Err(err);
+            return Err(err.wrap_err(Status::NoMatchingArtifact));
         }
     };
     let first_version = match valid_artifacts.first() {
         Some(v) => v,
         None => {
             log::error!("Valid artifacts empty?");
-            return Err(eyre::eyre!("Valid artifacts empty"));
+            return Err(eyre::eyre!("Valid artifacts empty").wrap_err(Status::NoMatchingArtifact));
         }
     };
     log::info!("Valid Artifact:{:?}", first_version);
 
     match cli.command {
-        cli::Commands::Check => {
-            println!("Name: {}", release.name);
-            println!("Version: {}", release.tag);
-            println!("TimeStamp: {}", release.created_at.format("%+"));
-            println!("{}", release.body.unwrap_or("".to_string()));
-            std::process::exit(100);
+        cli::Commands::Check { json } => {
+            let mut body = release.body.clone().unwrap_or_default();
+            // show what changed since the installed version, not only in the latest release
+            if let (
+                None,
+                source::ReleaseType::Stable | source::ReleaseType::Beta,
+                Some(installed),
+            ) = (&cli.target_version, &cli.release_type, &cli.prism_version)
+            {
+                let include_prereleases = cli.release_type == source::ReleaseType::Beta;
+                match skipped_release_notes(
+                    source.as_ref(),
+                    installed,
+                    &release,
+                    include_prereleases,
+                )
+                .await
+                {
+                    Ok(v) => body = v,
+                    Err(err) => log::warn!("Failed to gather skipped release notes: {:?}", err),
+                }
+            }
+            if let Err(err) = print_check(&cli, &release, &valid_artifacts, Some(&body), true, json)
+            {
+                log::error!("Failed to print check result: {:?}", err);
+                return Err(err);
+            }
+            return Ok(Status::UpdateAvailable);
         }
         cli::Commands::Update => {
             if installation_type == InstallationType::Appimage {
@@ -155,7 +281,7 @@ async fn main() -> Result<(), eyre::Report> {
                     Ok(_) => {}
                     Err(err) => {
                         log::error!("Failed to call appimage updater: {:?}", err);
-                        return Err(err);
+                        return Err(err.wrap_err(Status::InstallFailed));
                     }
                 };
             }
@@ -174,10 +300,10 @@ async fn main() -> Result<(), eyre::Report> {
                     return Err(err.into());
                 }
             };
-            let temp_dir_path = match cli.tmp_path {
+            let temp_dir_path = match &cli.tmp_path {
                 Some(v) => {
                     if v.exists() {
-                        match fs::remove_dir_all(&v) {
+                        match fs::remove_dir_all(v) {
                             Ok(_) => {}
                             Err(err) => {
                                 log::error!("Failed to create temporary directory: {:?}", err);
@@ -185,28 +311,81 @@ async fn main() -> Result<(), eyre::Report> {
                             }
                         };
                     }
-                    create_dir(&v)?;
-                    v
+                    create_dir(v)?;
+                    v.clone()
                 }
                 None => temp_dir.path().into(),
             };
 
-            let artifact_path = match fetch_url(
-                first_version.download_url.clone(),
+            let trusted_keys = match trusted_keys(&cli) {
+                Ok(v) => v,
+                Err(err) => {
+                    log::error!("Invalid public key: {:?}", err);
+                    return Err(err);
+                }
+            };
+            let github_token = match cli.github_token() {
+                Ok(v) => v,
+                Err(err) => {
+                    log::error!("Failed to read GitHub token: {:?}", err);
+                    return Err(err);
+                }
+            };
+            let sha256 =
+                match expected_sha256(&release, first_version, github_token.as_deref()).await {
+                    Ok(v) => v,
+                    Err(err) => {
+                        log::error!("Failed to get the artifact checksum: {:?}", err);
+                        return Err(err.wrap_err(Status::DownloadFailed));
+                    }
+                };
+            if sha256.is_none() {
+                log::warn!("No checksum published for {}", first_version.name);
+            }
+            // private assets can only be fetched through the API with a token
+            let artifact_path = match fetch_artifact(
+                first_version,
                 &temp_dir_path,
-                first_version.size_in_bytes,
+                github_token.as_deref(),
+                sha256.as_deref(),
             )
             .await
             {
                 Ok(v) => v,
                 Err(err) => {
                     log::error!("Failed to download artifact: {:?}", err);
-                    return Err(err);
+                    return Err(err.wrap_err(Status::DownloadFailed));
                 }
             };
             log::info!("downloaded to:{:?}", artifact_path);
+            if cli.no_verify_signature {
+                log::warn!("Signature verification is disabled");
+            } else if trusted_keys.is_empty() {
+                log::error!(
+                    "No public key configured to verify {}, pass --public-key or --no-verify-signature",
+                    first_version.name
+                );
+                return Err(
+                    eyre::eyre!("No public key configured").wrap_err(Status::SignatureInvalid)
+                );
+            } else if let Err(err) = verify_signature(
+                &release,
+                first_version,
+                &artifact_path,
+                &trusted_keys,
+                github_token.as_deref(),
+            )
+            .await
+            {
+                log::error!("Failed to verify signature: {:?}", err);
+                return Err(err.wrap_err(Status::SignatureInvalid));
+            }
             let final_path = match unarchive_loop(&artifact_path, &temp_dir_path) {
                 Ok(v) => v, // here start the updater again
+                Err(err) if err.is::<UnsafeArchive>() => {
+                    log::error!("Unsafe archive: {:?}", err);
+                    return Err(err.wrap_err(Status::InstallFailed));
+                }
                 Err(err) => {
                     log::info!("Nothing to unzip: {:?}", err);
                     artifact_path // execute this
@@ -224,23 +403,24 @@ async fn main() -> Result<(), eyre::Report> {
                     Ok(_) => {}
                     Err(err) => {
                         log::error!("Failed to backup: {:?}", err);
-                        return Err(err);
+                        return Err(err.wrap_err(Status::InstallFailed));
                     }
                 };
                 match move_with_manifest(
                     &final_path,
                     &root_dir,
-                    cli.build_artifact.unwrap().to_lowercase().contains("linux"),
+                    build_artifact.to_lowercase().contains("linux"),
                 ) {
                     Ok(_) => {}
                     Err(err) => {
                         log::error!("Failed to copy files: {:?}", err);
-                        return Err(err);
+                        return Err(err.wrap_err(Status::InstallFailed));
                     }
                 }
-                match cli.app_name {
+                match &cli.app_name {
                     None => {}
-                    Some(mut app_name) => {
+                    Some(app_name) => {
+                        let mut app_name = app_name.clone();
                         #[cfg(target_os = "windows")]
                         {
                             app_name += ".exe";
@@ -261,17 +441,23 @@ async fn main() -> Result<(), eyre::Report> {
                 };
             } else {
                 match run_installer(&final_path).await {
+                    Ok(v) if v.success() => {}
                     Ok(v) => {
-                        std::process::exit(v.code().unwrap_or(1));
+                        log::error!("Installer failed: {}", v);
+                        return Err(
+                            eyre::eyre!("Installer failed: {v}").wrap_err(Status::InstallFailed)
+                        );
                     }
                     Err(err) => {
                         log::error!("Failed to run installer: {:?}", err);
-                        return Err(err.into());
+                        return Err(eyre::Report::from(err).wrap_err(Status::InstallFailed));
                     }
                 };
             }
         }
+        // handled before looking for the latest release
+        cli::Commands::List { .. } => {}
     }
 
-    Ok(())
+    Ok(Status::Success)
 }
@@ -0,0 +1,54 @@
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use crate::cache::Cache;
+use crate::cli::CommandArgs;
+
+/// GETs `url` and decodes the JSON answer, failing on any non success status.
+///
+/// Answers are cached on disk and revalidated with their `ETag`.
+pub async fn get_json<T: DeserializeOwned>(
+    client: &Client,
+    cfg: &CommandArgs,
+    url: Url,
+) -> eyre::Result<T> {
+    let cache = Cache::new(cfg);
+    let cached = cache.load(url.as_str());
+    let mut request = client.get(url.clone());
+    if let Some(entry) = &cached {
+        if cache.is_fresh(entry) {
+            log::debug!("Using cached {url}");
+            return Ok(serde_json::from_str(&entry.body)?);
+        }
+        if let Some(etag) = &entry.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+    }
+
+    let response = request.send().await?;
+    if let (StatusCode::NOT_MODIFIED, Some(entry)) = (response.status(), &cached) {
+        log::debug!("{url} not modified");
+        cache.store(url.as_str(), entry.etag.clone(), &entry.body);
+        return Ok(serde_json::from_str(&entry.body)?);
+    }
+    let response = response.error_for_status()?;
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|val| val.to_str().ok())
+        .map(String::from);
+    let body = response.text().await?;
+    cache.store(url.as_str(), etag, &body);
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// Makes sure `url` ends with a slash so that `Url::join` appends to its path.
+pub fn as_base(url: &Url) -> Url {
+    let mut url = url.clone();
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    url
+}
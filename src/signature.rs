@@ -0,0 +1,283 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ed25519_dalek::{Signature, VerifyingKey};
+use std::fs;
+use std::path::Path;
+
+use crate::cli::CommandArgs;
+use crate::download::fetch_bytes;
+use crate::source::{PrismArtifact, PrismRelease};
+
+/// Keys trusted by every build, comma separated, set when compiling official releases.
+const EMBEDDED_KEYS: Option<&str> = option_env!("DISPERSION_PUBLIC_KEYS");
+
+const MINISIGN_EXTENSION: &str = ".minisig";
+const RAW_EXTENSION: &str = ".sig";
+
+/// Public key artifacts must be signed with.
+pub struct TrustedKey {
+    /// Minisign key ID, or the start of the key for raw ed25519 keys
+    id: String,
+    minisign: Option<minisign_verify::PublicKey>,
+    ed25519: VerifyingKey,
+}
+
+impl TrustedKey {
+    /// Parses a minisign public key (the second line of a `.pub` file) or a raw base64 ed25519 key.
+    fn parse(key: &str) -> eyre::Result<Self> {
+        // whole `.pub` files are accepted too, the key is on their last line
+        let key = key
+            .lines()
+            .map(str::trim)
+            .rfind(|line| !line.is_empty())
+            .unwrap_or_default();
+        let bytes = STANDARD.decode(key)?;
+        match bytes.len() {
+            // signature algorithm, key ID, key
+            42 => {
+                let key_id: [u8; 8] = bytes[2..10].try_into()?;
+                Ok(Self {
+                    id: format!("{:016X}", u64::from_le_bytes(key_id)),
+                    minisign: Some(minisign_verify::PublicKey::from_base64(key)?),
+                    ed25519: VerifyingKey::from_bytes(&bytes[10..42].try_into()?)?,
+                })
+            }
+            32 => Ok(Self {
+                id: bytes[..8]
+                    .iter()
+                    .map(|byte| format!("{byte:02X}"))
+                    .collect(),
+                minisign: None,
+                ed25519: VerifyingKey::from_bytes(&bytes[..].try_into()?)?,
+            }),
+            len => Err(eyre::eyre!(
+                "Public key of {len} bytes is neither minisign nor ed25519"
+            )),
+        }
+    }
+}
+
+/// Keys from `--public-key` and the ones embedded in this build.
+pub fn trusted_keys(cfg: &CommandArgs) -> eyre::Result<Vec<TrustedKey>> {
+    let embedded = EMBEDDED_KEYS
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty());
+    embedded
+        .chain(cfg.public_keys.iter().map(String::as_str))
+        .map(TrustedKey::parse)
+        .collect()
+}
+
+/// Whether `name` is a detached signature published next to the real assets.
+pub fn is_signature_file(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.ends_with(MINISIGN_EXTENSION) || name.ends_with(RAW_EXTENSION)
+}
+
+fn find_signature<'a>(
+    release: &'a PrismRelease,
+    artifact: &PrismArtifact,
+    extension: &str,
+) -> Option<&'a PrismArtifact> {
+    let name = format!("{}{extension}", artifact.name).to_lowercase();
+    release
+        .assets
+        .iter()
+        .find(|asset| asset.name.to_lowercase() == name)
+}
+
+/// Checks `file` against the `.minisig` or `.sig` companion of `artifact`.
+pub async fn verify_signature(
+    release: &PrismRelease,
+    artifact: &PrismArtifact,
+    file: &Path,
+    keys: &[TrustedKey],
+    token: Option<&str>,
+) -> eyre::Result<()> {
+    let data = fs::read(file)?;
+    if let Some(asset) = find_signature(release, artifact, MINISIGN_EXTENSION) {
+        let text = String::from_utf8(fetch_bytes(asset, token).await?)?;
+        let signature = minisign_verify::Signature::decode(&text)?;
+        for key in keys {
+            let Some(minisign) = &key.minisign else {
+                continue;
+            };
+            if minisign.verify(&data, &signature, false).is_ok() {
+                log::info!(
+                    "Signature of {} verified with key {}",
+                    artifact.name,
+                    key.id
+                );
+                return Ok(());
+            }
+        }
+        return Err(eyre::eyre!(
+            "{} of {} does not match any trusted key",
+            asset.name,
+            artifact.name
+        ));
+    }
+    if let Some(asset) = find_signature(release, artifact, RAW_EXTENSION) {
+        let bytes = fetch_bytes(asset, token).await?;
+        // raw 64 byte signatures, or the same in base64
+        let bytes = match bytes.len() {
+            64 => bytes,
+            _ => STANDARD.decode(String::from_utf8(bytes)?.trim())?,
+        };
+        let signature = Signature::from_slice(&bytes)?;
+        for key in keys {
+            if key.ed25519.verify_strict(&data, &signature).is_ok() {
+                log::info!(
+                    "Signature of {} verified with key {}",
+                    artifact.name,
+                    key.id
+                );
+                return Ok(());
+            }
+        }
+        return Err(eyre::eyre!(
+            "{} of {} does not match any trusted key",
+            asset.name,
+            artifact.name
+        ));
+    }
+    Err(eyre::eyre!("{} is not signed", artifact.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use ed25519_dalek::{Signer, SigningKey};
+    use tempfile::TempDir;
+    use url::Url;
+
+    const ARTIFACT: &str = "PrismLauncher-Linux-Qt6-Portable-9.2.tar.gz";
+    const DATA: &[u8] = b"PrismLauncher 9.2\n";
+    /// Key pair from the secret key `[7; 32]` with the minisign key ID `0807060504030201`.
+    const SECRET_KEY: [u8; 32] = [7; 32];
+    const MINISIGN_PUB: &str = "untrusted comment: minisign public key 0807060504030201
+RWQBAgMEBQYHCOpKbGPinFIKvvVQexMuxfmVR3auvr57kkIe6mkURtIs
+";
+    const RAW_PUB: &str = "6kpsY+KcUgq+9VB7Ey7F+ZVHdq6+vnuSQh7qaRRG0iw=";
+    /// `DATA` signed with that key the way `minisign -S` does it.
+    const MINISIG: &str = "untrusted comment: signature from minisign secret key
+RUQBAgMEBQYHCCzlPxWOxJqbsp6EBR8OoZZwnOM74dCfe8ekCRWACJEjJzgws9WVzoXn2a8+svIyzenk12be3QFJVgxRLapQmw4=
+trusted comment: timestamp:1735689600\tfile:PrismLauncher-Linux-Qt6-Portable-9.2.tar.gz
+pDx9FdEQrHtmm1k4RxODcP4Bc/1eirWfDxgZj6jVZ06kZ+wWRdkjnGelhdG7la32w73ZqC/zrH49f5LkzHhcBw==
+";
+
+    fn asset(dir: &TempDir, name: &str, contents: &[u8]) -> PrismArtifact {
+        let path = dir.path().join(name);
+        fs::write(&path, contents).unwrap();
+        PrismArtifact {
+            name: name.to_owned(),
+            size_in_bytes: contents.len(),
+            api_url: None,
+            download_url: Url::from_file_path(path).unwrap(),
+            mirrors: Vec::new(),
+            sha256: None,
+        }
+    }
+
+    /// Release of the artifact and the given companion files, all stored in `dir`.
+    fn release(dir: &TempDir, companions: &[(&str, &[u8])]) -> PrismRelease {
+        let mut assets = vec![asset(dir, ARTIFACT, DATA)];
+        for (extension, contents) in companions {
+            assets.push(asset(dir, &format!("{ARTIFACT}{extension}"), contents));
+        }
+        PrismRelease {
+            name: "9.2".to_owned(),
+            tag: "9.2".to_owned(),
+            created_at: Utc::now(),
+            assets,
+            body: None,
+            prerelease: false,
+        }
+    }
+
+    async fn verify(companions: &[(&str, &[u8])], key: &str) -> eyre::Result<()> {
+        let dir = TempDir::new().unwrap();
+        let release = release(&dir, companions);
+        let artifact = &release.assets[0];
+        let file = dir.path().join(ARTIFACT);
+        let keys = [TrustedKey::parse(key).unwrap()];
+        verify_signature(&release, artifact, &file, &keys, None).await
+    }
+
+    #[test]
+    fn parses_minisign_key() {
+        let key = TrustedKey::parse(MINISIGN_PUB).unwrap();
+        assert_eq!(key.id, "0807060504030201");
+        assert!(key.minisign.is_some());
+        assert_eq!(
+            key.ed25519,
+            SigningKey::from_bytes(&SECRET_KEY).verifying_key()
+        );
+        // the key line alone works as well
+        let line = MINISIGN_PUB.lines().nth(1).unwrap();
+        assert_eq!(TrustedKey::parse(line).unwrap().id, "0807060504030201");
+    }
+
+    #[test]
+    fn parses_raw_ed25519_key() {
+        let key = TrustedKey::parse(RAW_PUB).unwrap();
+        assert_eq!(key.id, "EA4A6C63E29C520A");
+        assert!(key.minisign.is_none());
+        assert_eq!(
+            key.ed25519,
+            SigningKey::from_bytes(&SECRET_KEY).verifying_key()
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        assert!(TrustedKey::parse("not base64!").is_err());
+        assert!(TrustedKey::parse(&STANDARD.encode([1; 16])).is_err());
+    }
+
+    #[tokio::test]
+    async fn accepts_valid_minisig() {
+        verify(&[(".minisig", MINISIG.as_bytes())], MINISIGN_PUB)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn accepts_valid_sig() {
+        let signature = SigningKey::from_bytes(&SECRET_KEY).sign(DATA).to_bytes();
+        verify(&[(".sig", &signature)], RAW_PUB).await.unwrap();
+        let encoded = format!("{}\n", STANDARD.encode(signature));
+        verify(&[(".sig", encoded.as_bytes())], MINISIGN_PUB)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_untrusted_key() {
+        let signature = SigningKey::from_bytes(&[9; 32]).sign(DATA).to_bytes();
+        assert!(verify(&[(".sig", &signature)], RAW_PUB).await.is_err());
+        // raw keys can not check minisign signatures
+        assert!(
+            verify(&[(".minisig", MINISIG.as_bytes())], RAW_PUB)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_artifact() {
+        let signature = SigningKey::from_bytes(&SECRET_KEY)
+            .sign(b"something else")
+            .to_bytes();
+        assert!(verify(&[(".sig", &signature)], RAW_PUB).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_unsigned_artifact() {
+        let err = verify(&[], MINISIGN_PUB).await.unwrap_err();
+        assert!(err.to_string().contains("is not signed"));
+    }
+}
@@ -0,0 +1,152 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::Deserialize;
+use url::Url;
+
+use crate::appcast::AppcastSource;
+use crate::cli::CommandArgs;
+use crate::feed::FeedSource;
+use crate::gitea::GiteaReleaseSource;
+use crate::github::{GithubReleaseSource, GithubWorkflowSource};
+use crate::gitlab::{GitlabPipelineSource, GitlabReleaseSource};
+use crate::local::LocalSource;
+
+#[derive(Debug, Clone, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseType {
+    Stable,
+    /// Stable releases plus the ones flagged as prerelease
+    Beta,
+    Nightly,
+}
+
+impl ReleaseType {
+    /// Whether a release published on `channel` is offered to this channel.
+    pub fn includes(&self, channel: &ReleaseType) -> bool {
+        match self {
+            ReleaseType::Beta => matches!(channel, ReleaseType::Stable | ReleaseType::Beta),
+            _ => self == channel,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum SourceType {
+    Github,
+    Gitea,
+    Gitlab,
+    Feed,
+    Appcast,
+    /// A directory of `<tag>/<assets>`, for offline machines
+    Local,
+}
+
+#[derive(Debug)]
+pub struct PrismRelease {
+    pub name: String,
+    pub tag: String,
+    pub created_at: DateTime<Utc>,
+    pub assets: Vec<PrismArtifact>,
+    pub body: Option<String>,
+    pub prerelease: bool,
+}
+
+#[derive(Debug)]
+pub struct PrismArtifact {
+    // pub node_id: String,
+    pub name: String,
+    pub size_in_bytes: usize,
+    /// API endpoint serving the asset to authenticated clients
+    pub api_url: Option<Url>,
+    pub download_url: Url,
+    /// Tried in order when `download_url` fails, see `PrismRelease::apply_mirrors`
+    pub mirrors: Vec<Url>,
+    /// Lowercase hex SHA-256 published by the source, if any
+    pub sha256: Option<String>,
+    // pub created_at: DateTime<Utc>,
+    // pub updated_at: DateTime<Utc>,
+}
+
+impl PrismRelease {
+    /// Fills the mirrors of every asset from `--mirror` templates.
+    ///
+    /// Placeholders: `{url}` the original url, `{host}`, `{path}` (query included),
+    /// `{tag}` and `{name}` the asset file name.
+    pub fn apply_mirrors(&mut self, templates: &[String]) -> eyre::Result<()> {
+        for asset in &mut self.assets {
+            let url = &asset.download_url;
+            let path = match url.query() {
+                Some(query) => format!("{}?{query}", url.path()),
+                None => url.path().to_owned(),
+            };
+            asset.mirrors = templates
+                .iter()
+                .map(|template| {
+                    template
+                        .replace("{url}", url.as_str())
+                        .replace("{host}", url.host_str().unwrap_or_default())
+                        .replace("{path}", path.trim_start_matches('/'))
+                        .replace("{tag}", &self.tag)
+                        .replace("{name}", &asset.name)
+                        .parse()
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        Ok(())
+    }
+}
+
+/// A backend able to tell which release should be installed.
+#[async_trait]
+pub trait ReleaseSource: Sync {
+    /// Returns the newest release available on this source.
+    async fn get_latest(&self) -> eyre::Result<PrismRelease>;
+
+    /// Returns the release published under `tag`, whatever its age.
+    async fn get_by_tag(&self, tag: &str) -> eyre::Result<PrismRelease> {
+        Err(eyre::eyre!("This source can not look up release {tag:?}"))
+    }
+
+    /// Returns one page of releases, newest first. Pages start at 1.
+    async fn list(&self, page: u32, per_page: u32) -> eyre::Result<Vec<PrismRelease>>;
+}
+
+/// One page of a listing that sources without server-side paging hold in full.
+pub fn paginate<T>(items: Vec<T>, page: u32, per_page: u32) -> impl Iterator<Item = T> {
+    let per_page = per_page as usize;
+    let skipped = (page as usize).saturating_sub(1).saturating_mul(per_page);
+    items.into_iter().skip(skipped).take(per_page)
+}
+
+/// Channel releases are looked up on, a pinned version is always a regular release.
+pub fn channel(cfg: &CommandArgs) -> &ReleaseType {
+    match cfg.target_version {
+        Some(_) => &ReleaseType::Stable,
+        None => &cfg.release_type,
+    }
+}
+
+pub fn get_source(cfg: &CommandArgs) -> eyre::Result<Box<dyn ReleaseSource + '_>> {
+    Ok(match (&cfg.source, channel(cfg)) {
+        (SourceType::Github, ReleaseType::Stable | ReleaseType::Beta) => {
+            Box::new(GithubReleaseSource::new(cfg)?)
+        }
+        (SourceType::Github, ReleaseType::Nightly) => Box::new(GithubWorkflowSource::new(cfg)?),
+        (SourceType::Gitea, ReleaseType::Stable | ReleaseType::Beta) => {
+            Box::new(GiteaReleaseSource::new(cfg)?)
+        }
+        (SourceType::Gitlab, ReleaseType::Stable | ReleaseType::Beta) => {
+            Box::new(GitlabReleaseSource::new(cfg)?)
+        }
+        (SourceType::Gitlab, ReleaseType::Nightly) => Box::new(GitlabPipelineSource::new(cfg)?),
+        (SourceType::Feed, _) => Box::new(FeedSource::new(cfg)?),
+        (SourceType::Appcast, _) => Box::new(AppcastSource::new(cfg)?),
+        (SourceType::Local, _) => Box::new(LocalSource::new(cfg)?),
+        (source, release_type) => {
+            return Err(eyre::eyre!(
+                "{release_type:?} releases are not supported by the {source:?} source"
+            ));
+        }
+    })
+}
@@ -5,7 +5,9 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::github::{PrismArtifact, PrismRelease};
+use crate::checksum::is_checksum_file;
+use crate::signature::is_signature_file;
+use crate::source::{PrismArtifact, PrismRelease};
 
 fn is_arm() -> bool {
     matches!(std::env::consts::ARCH, "arm" | "aarch64")
@@ -54,6 +56,7 @@ pub fn select_valid_artifacts(
         .assets
         .iter()
         .filter(|x| !x.name.ends_with(".zsync"))
+        .filter(|x| !is_checksum_file(&x.name) && !is_signature_file(&x.name))
         .filter(|x| {
             !((installation_type == InstallationType::Appimage)
                 ^ x.name.to_lowercase().ends_with("appimage"))
@@ -93,19 +96,18 @@ pub fn select_valid_artifacts(
                 return false;
             }
             let qt_pattern = Regex::new(r"-qt(\d+)").unwrap();
-            if let Some(captures) = qt_pattern.captures(&asset_name) {
-                if platform_qt_ver.is_empty()
+            if let Some(captures) = qt_pattern.captures(&asset_name)
+                && (platform_qt_ver.is_empty()
                     || platform_qt_ver.parse::<i32>().unwrap_or(0)
-                        != captures[1].parse::<i32>().unwrap_or(0)
-                {
-                    log::info!(
-                        "Rejecting {:?} because it is not for the correct qt version {:?} vs {:?}",
-                        asset_name,
-                        platform_qt_ver.parse::<i32>().unwrap_or(0),
-                        captures[1].parse::<i32>().unwrap_or(0)
-                    );
-                    return false;
-                }
+                        != captures[1].parse::<i32>().unwrap_or(0))
+            {
+                log::info!(
+                    "Rejecting {:?} because it is not for the correct qt version {:?} vs {:?}",
+                    asset_name,
+                    platform_qt_ver.parse::<i32>().unwrap_or(0),
+                    captures[1].parse::<i32>().unwrap_or(0)
+                );
+                return false;
             }
             log::info!("{:?} vs {:?}", installation_type, for_portable);
             (installation_type == InstallationType::Portable) == for_portable
@@ -118,13 +120,16 @@ pub fn select_valid_artifacts(
     Ok(artifacts)
 }
 
-fn parse_semver(input: &str) -> eyre::Result<Version> {
+pub fn parse_semver(input: &str) -> eyre::Result<Version> {
+    let input = input.trim().trim_start_matches('v');
+    // Keep the pre-release and build identifiers (-rc1, +build) aside
+    let (core, extra) = input.split_at(input.find(['-', '+']).unwrap_or(input.len()));
     // Split the input by dots
-    let parts: Vec<&str> = input.split('.').collect();
+    let parts: Vec<&str> = core.split('.').collect();
 
     // If there are 2 parts, add a third part (patch version) as "0"
     let full_semver_str = if parts.len() == 2 {
-        format!("{}.{}.0", parts[0], parts[1])
+        format!("{}.{}.0{}", parts[0], parts[1], extra)
     } else {
         input.to_string()
     };
@@ -150,3 +155,23 @@ pub fn compare_tags(v1: String, v2: String) -> eyre::Result<bool> {
     };
     Ok(v1 > v2)
 }
+
+/// Picks the release with the highest version, pre-release identifiers included.
+pub fn newest_release(releases: Vec<PrismRelease>) -> Option<PrismRelease> {
+    releases
+        .into_iter()
+        .filter_map(|release| match parse_semver(&release.tag) {
+            Ok(version) => Some((version, release)),
+            Err(_) => {
+                log::info!("Ignoring {:?} because it is not a version", release.tag);
+                None
+            }
+        })
+        .max_by(|(v1, _), (v2, _)| v1.cmp(v2))
+        .map(|(_, release)| release)
+}
+
+/// Whether `tag` carries pre-release identifiers (e.g. `9.0-rc1`).
+pub fn is_prerelease_tag(tag: &str) -> bool {
+    parse_semver(tag).is_ok_and(|version| !version.pre.is_empty())
+}
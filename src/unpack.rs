@@ -1,19 +1,143 @@
+use chrono::{Local, NaiveDate};
 use flate2::read::GzDecoder;
+use std::fmt;
 use std::fs::{self, File};
 use std::io;
-use std::path::{Path, PathBuf};
-use tar::Archive;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+use tar::{Archive, EntryType};
 use zip::read::ZipArchive;
 
+/// An archive entry would be written outside of the extraction directory.
+#[derive(Debug)]
+pub struct UnsafeArchive {
+    pub entry: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for UnsafeArchive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Refusing to extract {:?} from the archive: {}",
+            self.entry, self.reason
+        )
+    }
+}
+
+impl std::error::Error for UnsafeArchive {}
+
+fn unsafe_entry(entry: &Path, reason: &'static str) -> eyre::Report {
+    UnsafeArchive {
+        entry: entry.to_string_lossy().into_owned(),
+        reason,
+    }
+    .into()
+}
+
+/// Entry names must be relative and must not contain `..` at all.
+fn check_entry_path(path: &Path) -> eyre::Result<()> {
+    for component in path.components() {
+        match component {
+            Component::Normal(_) | Component::CurDir => {}
+            Component::ParentDir => {
+                return Err(unsafe_entry(
+                    path,
+                    "it contains a parent directory component",
+                ));
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(unsafe_entry(path, "it has an absolute path"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Symlink targets may climb out of the link's directory with leading `..` components as long
+/// as they stay inside `root`, the canonical destination. The directory is resolved on disk so
+/// that links extracted earlier can not be used to climb further.
+fn check_symlink(root: &Path, entry: &Path, target: &Path) -> eyre::Result<()> {
+    let escapes = || unsafe_entry(entry, "it links to a path outside of the destination");
+    let link = root.join(entry);
+    let parent = link.parent().unwrap_or(root);
+    fs::create_dir_all(parent)?;
+    let mut base = fs::canonicalize(parent)?
+        .strip_prefix(root)
+        .map_err(|_| escapes())?
+        .to_path_buf();
+    let mut descended = false;
+    for component in target.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if !descended => {
+                if !base.pop() {
+                    return Err(escapes());
+                }
+            }
+            Component::Normal(_) => descended = true,
+            _ => return Err(escapes()),
+        }
+    }
+    Ok(())
+}
+
+/// Modification time of a zip entry, zips store it in local time.
+fn zip_mtime(time: zip::DateTime) -> Option<SystemTime> {
+    let date = NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?;
+    let local = date
+        .and_hms_opt(
+            time.hour().into(),
+            time.minute().into(),
+            time.second().into(),
+        )?
+        .and_local_timezone(Local)
+        .earliest()?;
+    Some(local.into())
+}
+
 fn unarchive_tar_gz(src: &Path, dest: &Path) -> eyre::Result<()> {
     let file = File::open(src)?;
     let decompressor = GzDecoder::new(file);
     let mut archive = Archive::new(decompressor);
     // Create the destination directory if it doesn't exist
     fs::create_dir_all(dest)?;
+    let root = fs::canonicalize(dest)?;
 
-    // Extract files to the destination directory
-    archive.unpack(dest)?;
+    // Extract the entries one by one, `unpack` silently skips the unsafe ones
+    let mut directories = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        check_entry_path(&path)?;
+        // like `unpack`, directories come last so that their modes can not block their children
+        if entry.header().entry_type() == EntryType::Directory {
+            directories.push(entry);
+            continue;
+        }
+        if let Some(target) = entry.link_name()? {
+            match entry.header().entry_type() {
+                // symbolic links are relative to their own directory
+                EntryType::Symlink => check_symlink(&root, &path, &target)?,
+                // hard links are relative to the archive root
+                _ => check_entry_path(&target).map_err(|_| {
+                    unsafe_entry(&path, "it links to a path outside of the destination")
+                })?,
+            }
+        }
+        if !entry.unpack_in(dest)? {
+            return Err(unsafe_entry(&path, "it escapes the destination"));
+        }
+    }
+
+    // deepest first, a parent made read-only first would not let its children be updated
+    directories.sort_by(|a, b| b.path_bytes().cmp(&a.path_bytes()));
+    for mut directory in directories {
+        if !directory.unpack_in(dest)? {
+            let path = directory.path()?.into_owned();
+            return Err(unsafe_entry(&path, "it escapes the destination"));
+        }
+    }
 
     Ok(())
 }
@@ -24,23 +148,79 @@ fn unarchive_zip(src: &Path, dest: &Path) -> eyre::Result<()> {
 
     // Create the destination directory if it doesn't exist
     fs::create_dir_all(dest)?;
+    let root = fs::canonicalize(dest)?;
 
     // Extract all files in the archive
+    let mut directories = Vec::new();
     for i in 0..archive.len() {
         let mut file = archive.by_index(i)?;
-        let path = dest.join(file.name());
+        let name = PathBuf::from(file.name());
+        check_entry_path(&name)?;
+        let Some(relative) = file.enclosed_name() else {
+            return Err(unsafe_entry(&name, "it escapes the destination"));
+        };
+        let path = root.join(&relative);
 
-        if file.name().ends_with('/') {
+        let mtime = file.last_modified().and_then(zip_mtime);
+        if file.is_dir() {
             fs::create_dir_all(&path)?;
-        } else {
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            let mut out_file = File::create(&path)?;
-            io::copy(&mut file, &mut out_file)?;
+            directories.push((path, mtime, file.unix_mode()));
+            continue;
         }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if file.is_symlink() {
+            let mut target = String::new();
+            io::Read::read_to_string(&mut file, &mut target)?;
+            check_symlink(&root, &relative, Path::new(&target))?;
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&target, &path)?;
+            #[cfg(not(unix))]
+            fs::write(&path, target)?;
+            continue;
+        }
+        let mut out_file = File::create(&path)?;
+        io::copy(&mut file, &mut out_file)?;
+        restore_metadata(&out_file, mtime, file.unix_mode())?;
     }
 
+    // like tar, directories come last and deepest first so that their modes can not block
+    // writing their children, and writing the children does not change their mtime
+    directories.sort_by(|(a, ..), (b, ..)| b.cmp(a));
+    for (path, mtime, mode) in directories {
+        restore_dir_metadata(&path, mtime, mode)?;
+    }
+
+    Ok(())
+}
+
+fn restore_metadata(file: &File, mtime: Option<SystemTime>, mode: Option<u32>) -> io::Result<()> {
+    if let Some(mtime) = mtime {
+        file.set_modified(mtime)?;
+    }
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(mode & 0o777))?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restore_dir_metadata(
+    path: &Path,
+    mtime: Option<SystemTime>,
+    mode: Option<u32>,
+) -> io::Result<()> {
+    restore_metadata(&File::open(path)?, mtime, mode)
+}
+
+/// Directories can not be opened like files on Windows, their metadata is left as is.
+#[cfg(not(unix))]
+fn restore_dir_metadata(_: &Path, _: Option<SystemTime>, _: Option<u32>) -> io::Result<()> {
     Ok(())
 }
 
@@ -113,8 +293,225 @@ pub fn unarchive_loop(src: &Path, dir: &Path) -> eyre::Result<PathBuf> {
                 fs::remove_dir_all(new_path)?;
                 Ok(v)
             }
+            // a nested archive trying to escape fails the whole update
+            Err(err) if err.is::<UnsafeArchive>() => Err(err),
             Err(_) => Ok(files[0].clone()),
         },
         _ => Ok(new_path),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use tempfile::TempDir;
+    use zip::write::{SimpleFileOptions, ZipWriter};
+
+    /// Destination nested deep enough for `../..` to land in the temp dir.
+    fn destination(tmp: &TempDir) -> PathBuf {
+        tmp.path().join("a/b/out")
+    }
+
+    /// Builds a tar.gz with raw header names, `set_path` refuses the very names under test.
+    /// Directories are read-only to check that they are applied after their children.
+    fn tar_gz(tmp: &TempDir, entries: &[(&str, EntryType, &str)]) -> PathBuf {
+        let path = tmp.path().join("archive.tar.gz");
+        let encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (name, kind, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_entry_type(*kind);
+            header.set_mode(match kind {
+                EntryType::Directory => 0o555,
+                _ => 0o755,
+            });
+            let data = match kind {
+                EntryType::Symlink | EntryType::Link => {
+                    header.as_old_mut().linkname[..data.len()].copy_from_slice(data.as_bytes());
+                    ""
+                }
+                _ => data,
+            };
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, data.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+        path
+    }
+
+    enum ZipEntry<'a> {
+        File(&'a str, &'a str),
+        Symlink(&'a str, &'a str),
+    }
+
+    fn zip(tmp: &TempDir, entries: &[ZipEntry]) -> PathBuf {
+        let path = tmp.path().join("archive.zip");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        let options = SimpleFileOptions::default().unix_permissions(0o755);
+        for entry in entries {
+            match entry {
+                ZipEntry::File(name, data) => {
+                    writer.start_file(*name, options).unwrap();
+                    io::Write::write_all(&mut writer, data.as_bytes()).unwrap();
+                }
+                ZipEntry::Symlink(name, target) => {
+                    writer.add_symlink(*name, *target, options).unwrap();
+                }
+            }
+        }
+        writer.finish().unwrap();
+        path
+    }
+
+    fn assert_unsafe(result: eyre::Result<()>, tmp: &TempDir) {
+        let err = result.expect_err("the archive should be refused");
+        assert!(err.is::<UnsafeArchive>(), "unexpected error: {err:?}");
+        assert!(!tmp.path().join("escape").exists());
+    }
+
+    #[test]
+    fn tar_rejects_parent_directory_entries() {
+        let tmp = TempDir::new().unwrap();
+        let archive = tar_gz(&tmp, &[("../../escape", EntryType::Regular, "pwned")]);
+        assert_unsafe(unarchive_tar_gz(&archive, &destination(&tmp)), &tmp);
+    }
+
+    #[test]
+    fn tar_rejects_absolute_entries() {
+        let tmp = TempDir::new().unwrap();
+        let absolute = tmp.path().join("escape");
+        let archive = tar_gz(
+            &tmp,
+            &[(absolute.to_str().unwrap(), EntryType::Regular, "pwned")],
+        );
+        assert_unsafe(unarchive_tar_gz(&archive, &destination(&tmp)), &tmp);
+    }
+
+    #[test]
+    fn tar_rejects_escaping_links() {
+        for link in [EntryType::Symlink, EntryType::Link] {
+            let tmp = TempDir::new().unwrap();
+            let archive = tar_gz(&tmp, &[("link", link, "../../escape")]);
+            assert_unsafe(unarchive_tar_gz(&archive, &destination(&tmp)), &tmp);
+        }
+    }
+
+    #[test]
+    fn tar_rejects_symlink_chains() {
+        let tmp = TempDir::new().unwrap();
+        let archive = tar_gz(
+            &tmp,
+            &[
+                ("a", EntryType::Symlink, "."),
+                ("a/b", EntryType::Symlink, ".."),
+                ("a/b/escape", EntryType::Regular, "pwned"),
+            ],
+        );
+        assert_unsafe(unarchive_tar_gz(&archive, &destination(&tmp)), &tmp);
+    }
+
+    #[test]
+    fn tar_extracts_links_inside_the_destination() {
+        let tmp = TempDir::new().unwrap();
+        let dest = destination(&tmp);
+        let archive = tar_gz(
+            &tmp,
+            &[
+                ("lib/libfoo.so.1.2", EntryType::Regular, "so"),
+                ("lib/libfoo.so.1", EntryType::Symlink, "libfoo.so.1.2"),
+                ("bin/lib", EntryType::Symlink, "../lib"),
+            ],
+        );
+        unarchive_tar_gz(&archive, &dest).unwrap();
+        assert_eq!(
+            fs::read_to_string(dest.join("bin/lib/libfoo.so.1")).unwrap(),
+            "so"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tar_applies_directories_last() {
+        use std::os::unix::fs::PermissionsExt;
+        let tmp = TempDir::new().unwrap();
+        let dest = destination(&tmp);
+        let archive = tar_gz(
+            &tmp,
+            &[
+                ("ro/", EntryType::Directory, ""),
+                ("ro/file", EntryType::Regular, "x"),
+            ],
+        );
+        unarchive_tar_gz(&archive, &dest).unwrap();
+        assert_eq!(fs::read_to_string(dest.join("ro/file")).unwrap(), "x");
+        let mode = fs::metadata(dest.join("ro")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o555);
+        // let the temp dir clean up after itself
+        fs::set_permissions(dest.join("ro"), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn zip_rejects_parent_directory_entries() {
+        let tmp = TempDir::new().unwrap();
+        let archive = zip(&tmp, &[ZipEntry::File("../../escape", "pwned")]);
+        assert_unsafe(unarchive_zip(&archive, &destination(&tmp)), &tmp);
+    }
+
+    #[test]
+    fn zip_rejects_absolute_entries() {
+        let tmp = TempDir::new().unwrap();
+        let absolute = tmp.path().join("escape");
+        let archive = zip(&tmp, &[ZipEntry::File(absolute.to_str().unwrap(), "pwned")]);
+        assert_unsafe(unarchive_zip(&archive, &destination(&tmp)), &tmp);
+    }
+
+    #[test]
+    fn zip_rejects_escaping_symlinks() {
+        let tmp = TempDir::new().unwrap();
+        let archive = zip(&tmp, &[ZipEntry::Symlink("link", "../../escape")]);
+        assert_unsafe(unarchive_zip(&archive, &destination(&tmp)), &tmp);
+    }
+
+    #[test]
+    fn zip_rejects_symlink_chains() {
+        let tmp = TempDir::new().unwrap();
+        let archive = zip(
+            &tmp,
+            &[
+                ZipEntry::Symlink("a", "."),
+                ZipEntry::Symlink("a/b", ".."),
+                ZipEntry::File("a/b/escape", "pwned"),
+            ],
+        );
+        assert_unsafe(unarchive_zip(&archive, &destination(&tmp)), &tmp);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn zip_keeps_modes_and_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+        let tmp = TempDir::new().unwrap();
+        let dest = destination(&tmp);
+        let archive = zip(
+            &tmp,
+            &[
+                ZipEntry::File("bin/prismlauncher", "#!/bin/sh"),
+                ZipEntry::File("lib/libfoo.so.1.2", "so"),
+                ZipEntry::Symlink("lib/libfoo.so.1", "libfoo.so.1.2"),
+            ],
+        );
+        unarchive_zip(&archive, &dest).unwrap();
+        let mode = fs::metadata(dest.join("bin/prismlauncher"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+        let link = dest.join("lib/libfoo.so.1");
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(link).unwrap(), "so");
+    }
+}